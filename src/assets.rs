@@ -2,6 +2,8 @@ use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
 
+use crate::effects::EffectKind;
+
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
     #[asset(path = "sounds/click.wav")]
@@ -65,6 +67,18 @@ pub struct UIImageAssets {
     #[asset(path = "textures/ui/icons/icon_HP.png")]
     pub icon_hp: Handle<Image>,
 
+    // 状态效果
+    #[asset(path = "textures/ui/icons/icon_Poison.png")]
+    pub icon_poison: Handle<Image>,
+    #[asset(path = "textures/ui/icons/icon_Burn.png")]
+    pub icon_burn: Handle<Image>,
+    #[asset(path = "textures/ui/icons/icon_Shield.png")]
+    pub icon_shield: Handle<Image>,
+    #[asset(path = "textures/ui/icons/icon_Weaken.png")]
+    pub icon_weaken: Handle<Image>,
+    #[asset(path = "textures/ui/icons/icon_Haste.png")]
+    pub icon_haste: Handle<Image>,
+
     #[asset(path = "textures/ui/components/status_hub_panel.png")]
     pub status_hub_panel: Handle<Image>,
 
@@ -94,3 +108,15 @@ pub struct UIImageAssets {
     #[asset(path = "textures/ui/skill_icons/skill_icon2.png")]
     pub _s2: Handle<Image>,
 }
+
+impl UIImageAssets {
+    pub fn effect_icon(&self, kind: EffectKind) -> Handle<Image> {
+        match kind {
+            EffectKind::Poison => self.icon_poison.clone_weak(),
+            EffectKind::Burn => self.icon_burn.clone_weak(),
+            EffectKind::Shield => self.icon_shield.clone_weak(),
+            EffectKind::Weaken => self.icon_weaken.clone_weak(),
+            EffectKind::Haste => self.icon_haste.clone_weak(),
+        }
+    }
+}
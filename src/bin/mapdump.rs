@@ -0,0 +1,54 @@
+use que::regions::{generators::get_generator_config, Regions, SelectedLevel};
+use que::rng::{self, RngStreams};
+
+/*
+    不启动窗口, 按种子生成地图并导出为 ascii / json / svg
+    cargo run --bin mapdump -- --seed 42 --scope 32x32 --out map
+    指定 --level 时导出关卡的布局, 忽略 --scope
+*/
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let arg = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    };
+    let seed = match arg("--seed") {
+        Some(seed) => seed.parse::<u64>().expect("--seed must be a u64"),
+        None => rand::random::<u64>(),
+    };
+    let out = arg("--out").unwrap_or_else(|| "map".to_string());
+
+    let config = get_generator_config();
+    let mut regions = Regions::default();
+    regions.scope = match arg("--scope") {
+        Some(scope) => {
+            let (x, y) = scope.split_once('x').expect("--scope must look like 32x32");
+            (
+                x.parse().expect("invalid scope width"),
+                y.parse().expect("invalid scope height"),
+            )
+        }
+        None => config.scope,
+    };
+    let mut streams = RngStreams::new(seed);
+    let connectivity = match SelectedLevel::from_args().0 {
+        Some(level) => regions.generate(&level, streams.stream(rng::LAYOUT)),
+        None => regions.generate(&*config.build(), streams.stream(rng::LAYOUT)),
+    };
+
+    std::fs::write(format!("{}.txt", out), regions.to_ascii()).unwrap();
+    std::fs::write(format!("{}.json", out), regions.to_json()).unwrap();
+    std::fs::write(format!("{}.svg", out), regions.to_svg(16.)).unwrap();
+    println!(
+        "seed {} scope {:?}: {} tiles, {} pruned -> {}.txt {}.json {}.svg",
+        seed,
+        regions.scope,
+        connectivity.reachable,
+        connectivity.pruned.len(),
+        out,
+        out,
+        out
+    );
+}
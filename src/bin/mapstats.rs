@@ -0,0 +1,119 @@
+use que::regions::{generators::get_generator_config, MapStats, Regions};
+use que::rng::{self, RngStreams};
+
+// 一组数值的分布
+struct Summary(Vec<f64>);
+
+impl Summary {
+    fn percentile(&self, p: f64) -> f64 {
+        let mut values = self.0.clone();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let index = ((values.len() - 1) as f64 * p).round() as usize;
+        values[index]
+    }
+
+    fn mean(&self) -> f64 {
+        self.0.iter().sum::<f64>() / self.0.len() as f64
+    }
+
+    fn print(&self, name: &str) {
+        println!(
+            "{:<18} min {:>8.2}  p10 {:>8.2}  p50 {:>8.2}  p90 {:>8.2}  max {:>8.2}  mean {:>8.2}",
+            name,
+            self.percentile(0.),
+            self.percentile(0.1),
+            self.percentile(0.5),
+            self.percentile(0.9),
+            self.percentile(1.),
+            self.mean()
+        );
+    }
+}
+
+/*
+    对大量种子运行地图生成器, 输出统计分布并检查地图数据的约束
+    存在违反约束的地图时以非0状态码退出
+    cargo run --release --bin mapstats -- --seeds 1000 --from 0
+*/
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let arg = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .map(|value| value.parse::<u64>().expect("expect a u64 argument"))
+    };
+    let seeds = arg("--seeds").unwrap_or(1000);
+    let from = arg("--from").unwrap_or(0);
+    if seeds == 0 {
+        return;
+    }
+
+    let config = get_generator_config();
+    let generator = config.build();
+    let mut all_stats = vec![];
+    let mut failed = 0;
+    let mut invalid = 0;
+    for seed in from..from + seeds {
+        let mut regions = Regions::default();
+        regions.scope = config.scope;
+        let mut streams = RngStreams::new(seed);
+        let connectivity = regions.generate(&*generator, streams.stream(rng::LAYOUT));
+        let stats = MapStats::collect(&regions, &connectivity);
+        // 只剩起始方块视为生成失败
+        if stats.tiles <= 1 {
+            failed += 1;
+        }
+        let violations = regions.validate();
+        if !violations.is_empty() {
+            invalid += 1;
+            println!("seed {}: {} violations", seed, violations.len());
+            for violation in violations.iter().take(10) {
+                println!("    {:?}", violation);
+            }
+        }
+        all_stats.push(stats);
+    }
+
+    let summary = |f: &dyn Fn(&MapStats) -> f64| Summary(all_stats.iter().map(f).collect());
+    println!(
+        "{} seeds from {}, generator {:?}",
+        seeds, from, config.generator
+    );
+    summary(&|s| s.tiles as f64).print("tiles");
+    summary(&|s| s.cells as f64).print("cells");
+    summary(&|s| s.rooms as f64 / s.tiles as f64).print("room ratio");
+    summary(&|s| s.obstacles as f64 / s.tiles as f64).print("obstacle ratio");
+    summary(&|s| s.starts as f64 / s.tiles as f64).print("start ratio");
+    summary(&|s| s.corridors as f64 / s.tiles as f64).print("corridor ratio");
+    summary(&|s| s.specials as f64 / s.tiles as f64).print("special ratio");
+    summary(&|s| s.components.len() as f64).print("components");
+    summary(&|s| s.components.first().copied().unwrap_or(0) as f64).print("largest component");
+    summary(&|s| s.pruned as f64).print("pruned tiles");
+    summary(&|s| s.diameter as f64).print("diameter");
+    summary(&|s| (s.no_room + s.already_placed) as f64).print("discarded tiles");
+    let percent = |count: usize| count as f64 * 100. / seeds as f64;
+    let pruned_any = all_stats.iter().filter(|s| s.pruned > 0).count();
+    let discarded_any = all_stats
+        .iter()
+        .filter(|s| s.no_room + s.already_placed > 0)
+        .count();
+    println!(
+        "failed {} ({:.2}%), pruned any {} ({:.2}%), discarded any {} ({:.2}%), invalid {}",
+        failed,
+        percent(failed),
+        pruned_any,
+        percent(pruned_any),
+        discarded_any,
+        percent(discarded_any),
+        invalid
+    );
+    println!(
+        "discarded no room {}, already placed {}",
+        all_stats.iter().map(|s| s.no_room).sum::<usize>(),
+        all_stats.iter().map(|s| s.already_placed).sum::<usize>()
+    );
+    if invalid > 0 {
+        std::process::exit(1);
+    }
+}
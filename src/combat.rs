@@ -0,0 +1,489 @@
+/*
+    战斗结算, 不依赖 ECS, 玩家与怪物的伤害都由这里计算
+*/
+use serde::{Deserialize, Serialize};
+
+use crate::effects::{Effect, EffectKind, StatusEffects};
+
+// 任何命中至少造成的伤害, 防御再高也不会让攻击变成治疗
+pub const MIN_DAMAGE: i64 = 1;
+
+// 特性施加的状态效果持续的回合数
+pub const TRAIT_EFFECT_TURNS: u32 = 3;
+
+// 伤害类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageType {
+    Physical, // 受防御减免
+    True,     // 无视防御
+}
+
+// 怪物的特性, 在 monsters.ron 中配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Trait {
+    FirstStrike,        // 先手, 被攻击时先于对方出手
+    ArmorPiercing(i64), // 破甲, 攻击时无视对方若干点防御
+    Regeneration(i64),  // 再生, 每回合结束时恢复生命
+    Poison(i64),        // 毒, 命中时使对方中毒
+    Burn(i64),          // 灼烧, 命中时使对方灼烧
+    Weaken(i64),        // 虚弱, 命中时使对方虚弱
+    Thorns(i64),        // 荆棘, 被命中时反弹伤害给攻击者
+    Explode(i64),       // 自爆, 被击倒时对攻击者与相邻区域造成伤害
+}
+
+impl Trait {
+    pub fn label(&self) -> String {
+        match self {
+            Trait::FirstStrike => "先手".to_string(),
+            Trait::ArmorPiercing(n) => format!("破甲{}", n),
+            Trait::Regeneration(n) => format!("再生{}", n),
+            Trait::Poison(n) => format!("毒{}", n),
+            Trait::Burn(n) => format!("灼{}", n),
+            Trait::Weaken(n) => format!("弱{}", n),
+            Trait::Thorns(n) => format!("荆棘{}", n),
+            Trait::Explode(n) => format!("自爆{}", n),
+        }
+    }
+
+    // 命中时施加给对方的状态效果
+    pub fn inflicts(&self) -> Option<Effect> {
+        let (kind, stacks) = match self {
+            Trait::Poison(n) => (EffectKind::Poison, *n),
+            Trait::Burn(n) => (EffectKind::Burn, *n),
+            Trait::Weaken(n) => (EffectKind::Weaken, *n),
+            _ => return None,
+        };
+        Some(Effect::new(kind, stacks, TRAIT_EFFECT_TURNS))
+    }
+}
+
+// 参与战斗的一方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Combatant {
+    pub atk: i64,
+    pub def: i64,
+    pub hp: i64,
+    pub max_hp: i64,
+    pub traits: Vec<Trait>,
+    pub effects: StatusEffects,
+}
+
+impl Combatant {
+    fn has(&self, t: Trait) -> bool {
+        self.traits.contains(&t)
+    }
+
+    // 同类特性的数值之和
+    fn sum(&self, pick: impl Fn(&Trait) -> Option<i64>) -> i64 {
+        self.traits.iter().filter_map(pick).sum()
+    }
+
+    fn piercing(&self) -> i64 {
+        self.sum(|t| match t {
+            Trait::ArmorPiercing(n) => Some(*n),
+            _ => None,
+        })
+    }
+
+    fn thorns(&self) -> i64 {
+        self.sum(|t| match t {
+            Trait::Thorns(n) => Some(*n),
+            _ => None,
+        })
+    }
+
+    fn regeneration(&self) -> i64 {
+        self.sum(|t| match t {
+            Trait::Regeneration(n) => Some(*n),
+            _ => None,
+        })
+    }
+
+    fn explosion(&self) -> i64 {
+        self.sum(|t| match t {
+            Trait::Explode(n) => Some(*n),
+            _ => None,
+        })
+    }
+}
+
+// 一次攻击, multiplier 为攻击力的倍数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attack {
+    pub multiplier: i64,
+    pub damage_type: DamageType,
+}
+
+impl Attack {
+    pub const BASIC: Attack = Attack {
+        multiplier: 1,
+        damage_type: DamageType::Physical,
+    };
+}
+
+// 一回合的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub damage: i64,      // 攻击方造成的伤害
+    pub counter: i64,     // 防守方出手造成的伤害, 防守方被击倒且没有先手时为 0
+    pub reflected: i64,   // 荆棘反弹给攻击方的伤害
+    pub explosion: i64,   // 防守方被击倒时自爆的伤害, 由调用方施加到相邻区域
+    pub attacker_hp: i64, // 结算后攻击方的生命
+    pub defender_hp: i64, // 结算后防守方的生命
+    pub attacker_effects: StatusEffects,
+    pub defender_effects: StatusEffects,
+}
+
+impl Outcome {
+    pub fn defender_defeated(&self) -> bool {
+        self.defender_hp <= 0
+    }
+
+    pub fn attacker_defeated(&self) -> bool {
+        self.attacker_hp <= 0
+    }
+}
+
+// 单次命中的伤害
+pub fn damage(atk: i64, def: i64, attack: &Attack) -> i64 {
+    let raw = atk * attack.multiplier;
+    let damage = match attack.damage_type {
+        DamageType::Physical => raw - def,
+        DamageType::True => raw,
+    };
+    damage.max(MIN_DAMAGE)
+}
+
+// 回合开始时结算状态效果, 返回损失的生命
+pub fn start_turn(combatant: &mut Combatant) -> i64 {
+    let damage = combatant.effects.on_turn_start();
+    combatant.hp -= damage;
+    damage
+}
+
+// from 命中 to, 返回造成的伤害与被荆棘反弹的伤害
+fn hit(from: &mut Combatant, to: &mut Combatant, attack: &Attack) -> (i64, i64) {
+    let atk = from.effects.on_attack(from.atk);
+    let def = (to.def + to.effects.def_modifier() - from.piercing()).max(0);
+    let dealt = to.effects.on_hit(damage(atk, def, attack));
+    to.hp -= dealt;
+    for effect in from.traits.iter().filter_map(Trait::inflicts) {
+        to.effects.add(effect);
+    }
+    let reflected = to.thorns();
+    from.hp -= reflected;
+    (dealt, reflected)
+}
+
+// 一次出手, 疾速时追加普通攻击
+fn strike(from: &mut Combatant, to: &mut Combatant, attack: &Attack) -> (i64, i64) {
+    let (mut dealt, mut reflected) = hit(from, to, attack);
+    for _ in 0..from.effects.extra_hits() {
+        if from.hp > 0 && to.hp > 0 {
+            let (more, thorns) = hit(from, to, &Attack::BASIC);
+            dealt += more;
+            reflected += thorns;
+        }
+    }
+    (dealt, reflected)
+}
+
+/*
+    攻击方使用 skill 攻击防守方, 防守方若未被击倒则以普通攻击反击
+    防守方有先手时改为在攻击前出手, 攻击方被先手击倒则不会出手
+    回合结束时存活的一方再生, 防守方被击倒时自爆
+*/
+pub fn resolve(attacker: &Combatant, defender: &Combatant, skill: &Attack) -> Outcome {
+    let mut a = attacker.clone();
+    let mut d = defender.clone();
+    let first_strike = d.has(Trait::FirstStrike);
+    let mut counter = 0;
+    let mut damage = 0;
+    let mut reflected = 0;
+    if first_strike {
+        counter += strike(&mut d, &mut a, &Attack::BASIC).0;
+    }
+    if a.hp > 0 {
+        let (dealt, thorns) = strike(&mut a, &mut d, skill);
+        damage = dealt;
+        reflected = thorns;
+        if !first_strike && d.hp > 0 {
+            counter += strike(&mut d, &mut a, &Attack::BASIC).0;
+        }
+    }
+    for c in [&mut a, &mut d] {
+        if c.hp > 0 {
+            c.hp = (c.hp + c.regeneration()).min(c.max_hp.max(c.hp));
+        }
+    }
+    let explosion = if d.hp <= 0 { d.explosion() } else { 0 };
+    a.hp -= explosion;
+    Outcome {
+        damage,
+        counter,
+        reflected,
+        explosion,
+        attacker_hp: a.hp,
+        defender_hp: d.hp,
+        attacker_effects: a.effects,
+        defender_effects: d.effects,
+    }
+}
+
+// 预测的回合数上限, 超过时视为打不死
+pub const MAX_EXCHANGES: usize = 99;
+
+// 按技能轮转打完一场战斗的预测
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forecast {
+    pub damages: Vec<i64>, // 每回合造成的伤害, 护盾 疾速 灼烧等会让各回合不同
+    pub exchanges: Option<usize>, // 击倒对方需要的回合数, 打不死或先被击倒时为 None
+    pub hp_lost: i64,      // 战斗结束时损失的生命
+    pub lethal: bool,      // 战斗会让攻击方倒下
+}
+
+/*
+    攻击方依次使用 rotation 中的招式与防守方交战, 直到一方倒下或招式用完
+    每回合开始时双方先结算状态效果
+*/
+pub fn forecast(attacker: &Combatant, defender: &Combatant, rotation: &[Attack]) -> Forecast {
+    let mut attacker_now = attacker.clone();
+    let mut defender_now = defender.clone();
+    let mut damages = vec![];
+    let mut exchanges = None;
+    let mut lethal = false;
+    for (i, attack) in rotation.iter().enumerate() {
+        start_turn(&mut attacker_now);
+        start_turn(&mut defender_now);
+        let outcome = resolve(&attacker_now, &defender_now, attack);
+        damages.push(outcome.damage);
+        attacker_now.hp = outcome.attacker_hp;
+        defender_now.hp = outcome.defender_hp;
+        attacker_now.effects = outcome.attacker_effects.clone();
+        defender_now.effects = outcome.defender_effects.clone();
+        if outcome.defender_defeated() {
+            exchanges = Some(i + 1);
+            break;
+        }
+        if outcome.attacker_defeated() {
+            lethal = true;
+            break;
+        }
+    }
+    Forecast {
+        damages,
+        exchanges,
+        hp_lost: attacker.hp - attacker_now.hp,
+        lethal,
+    }
+}
+
+impl Forecast {
+    // 各回合伤害的最小值与最大值, 没有交手时为 None
+    pub fn damage_range(&self) -> Option<(i64, i64)> {
+        let min = self.damages.iter().copied().min()?;
+        let max = self.damages.iter().copied().max()?;
+        Some((min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combatant(atk: i64, def: i64, hp: i64) -> Combatant {
+        Combatant {
+            atk,
+            def,
+            hp,
+            max_hp: hp,
+            traits: vec![],
+            effects: StatusEffects::default(),
+        }
+    }
+
+    fn with_effects(mut c: Combatant, effects: &[Effect]) -> Combatant {
+        c.effects = StatusEffects(effects.to_vec());
+        c
+    }
+
+    fn with_traits(mut c: Combatant, traits: &[Trait]) -> Combatant {
+        c.traits = traits.to_vec();
+        c
+    }
+
+    #[test]
+    fn physical_damage_is_reduced_by_def() {
+        assert_eq!(damage(10, 3, &Attack::BASIC), 7);
+    }
+
+    #[test]
+    fn high_def_never_heals() {
+        assert_eq!(damage(2, 50, &Attack::BASIC), MIN_DAMAGE);
+        let outcome = resolve(&combatant(2, 0, 10), &combatant(1, 50, 10), &Attack::BASIC);
+        assert_eq!(outcome.damage, MIN_DAMAGE);
+        assert_eq!(outcome.defender_hp, 10 - MIN_DAMAGE);
+    }
+
+    #[test]
+    fn multiplier_applies_before_def() {
+        let double = Attack {
+            multiplier: 2,
+            damage_type: DamageType::Physical,
+        };
+        assert_eq!(damage(10, 5, &double), 15);
+    }
+
+    #[test]
+    fn true_damage_ignores_def() {
+        let attack = Attack {
+            multiplier: 1,
+            damage_type: DamageType::True,
+        };
+        assert_eq!(damage(10, 8, &attack), 10);
+    }
+
+    #[test]
+    fn survivor_counter_attacks() {
+        let outcome = resolve(&combatant(10, 2, 30), &combatant(6, 1, 20), &Attack::BASIC);
+        assert_eq!(outcome.damage, 9);
+        assert_eq!(outcome.defender_hp, 11);
+        assert_eq!(outcome.counter, 4);
+        assert_eq!(outcome.attacker_hp, 26);
+        assert!(!outcome.defender_defeated());
+    }
+
+    #[test]
+    fn forecast_follows_rotation() {
+        let double = Attack {
+            multiplier: 2,
+            damage_type: DamageType::Physical,
+        };
+        let rotation = [Attack::BASIC, double, Attack::BASIC, double];
+        // 伤害依次为 8, 18, 8, 第三回合击倒, 前两回合各受到 3 点反击
+        let forecast = forecast(&combatant(10, 2, 30), &combatant(5, 2, 30), &rotation);
+        assert_eq!(forecast.damages, vec![8, 18, 8]);
+        assert_eq!(forecast.damage_range(), Some((8, 18)));
+        assert_eq!(forecast.exchanges, Some(3));
+        assert_eq!(forecast.hp_lost, 6);
+        assert!(!forecast.lethal);
+    }
+
+    #[test]
+    fn forecast_warns_lethal() {
+        let rotation = [Attack::BASIC; MAX_EXCHANGES];
+        let forecast = forecast(&combatant(3, 0, 10), &combatant(20, 0, 100), &rotation);
+        assert!(forecast.lethal);
+        assert_eq!(forecast.exchanges, None);
+        assert_eq!(forecast.hp_lost, 20);
+    }
+
+    #[test]
+    fn forecast_shows_shield_absorbing_early_hits() {
+        let defender = with_effects(
+            combatant(5, 2, 30),
+            &[Effect::new(EffectKind::Shield, 10, 3)],
+        );
+        let rotation = [Attack::BASIC; 4];
+        let forecast = forecast(&combatant(10, 2, 30), &defender, &rotation);
+        // 护盾先抵挡 8 点, 再抵挡剩下的 2 点
+        assert_eq!(forecast.damages, vec![0, 6, 8, 8]);
+        assert_eq!(forecast.damage_range(), Some((0, 8)));
+    }
+
+    #[test]
+    fn first_strike_hits_before_attack() {
+        let defender = with_traits(combatant(10, 0, 50), &[Trait::FirstStrike]);
+        let outcome = resolve(&combatant(5, 0, 10), &defender, &Attack::BASIC);
+        assert_eq!(outcome.counter, 10);
+        assert_eq!(outcome.damage, 0);
+        assert_eq!(outcome.defender_hp, 50);
+        assert!(outcome.attacker_defeated());
+
+        // 先手后不再反击
+        let outcome = resolve(&combatant(5, 0, 30), &defender, &Attack::BASIC);
+        assert_eq!(outcome.counter, 10);
+        assert_eq!(outcome.attacker_hp, 20);
+        assert_eq!(outcome.defender_hp, 45);
+    }
+
+    #[test]
+    fn armor_piercing_and_poison() {
+        let defender = with_traits(
+            combatant(6, 0, 40),
+            &[Trait::ArmorPiercing(3), Trait::Poison(2)],
+        );
+        let outcome = resolve(&combatant(10, 4, 40), &defender, &Attack::BASIC);
+        // 6 - (4 - 3)
+        assert_eq!(outcome.counter, 5);
+        assert_eq!(
+            outcome.attacker_effects,
+            StatusEffects(vec![Effect::new(EffectKind::Poison, 2, TRAIT_EFFECT_TURNS)])
+        );
+    }
+
+    #[test]
+    fn shield_absorbs_and_is_consumed() {
+        let defender = with_effects(
+            combatant(1, 0, 40),
+            &[Effect::new(EffectKind::Shield, 4, 3)],
+        );
+        let outcome = resolve(&combatant(10, 0, 20), &defender, &Attack::BASIC);
+        assert_eq!(outcome.damage, 6);
+        assert!(outcome.defender_effects.0.is_empty());
+    }
+
+    #[test]
+    fn weaken_burn_and_haste() {
+        let attacker = with_effects(
+            combatant(10, 0, 20),
+            &[
+                Effect::new(EffectKind::Weaken, 3, 2),
+                Effect::new(EffectKind::Haste, 1, 2),
+            ],
+        );
+        let defender = with_effects(combatant(1, 4, 40), &[Effect::new(EffectKind::Burn, 2, 2)]);
+        let outcome = resolve(&attacker, &defender, &Attack::BASIC);
+        // (10 - 3) - (4 - 2) 两次
+        assert_eq!(outcome.damage, 10);
+    }
+
+    #[test]
+    fn thorns_reflect_to_attacker() {
+        let defender = with_traits(combatant(1, 0, 40), &[Trait::Thorns(3)]);
+        let outcome = resolve(&combatant(10, 1, 20), &defender, &Attack::BASIC);
+        assert_eq!(outcome.reflected, 3);
+        assert_eq!(outcome.attacker_hp, 20 - 3 - MIN_DAMAGE);
+    }
+
+    #[test]
+    fn regeneration_is_capped() {
+        let mut defender = with_traits(combatant(1, 0, 40), &[Trait::Regeneration(5)]);
+        let outcome = resolve(&combatant(8, 0, 20), &defender, &Attack::BASIC);
+        assert_eq!(outcome.defender_hp, 37);
+        defender.hp = 38;
+        defender.max_hp = 40;
+        let outcome = resolve(&combatant(2, 0, 20), &defender, &Attack::BASIC);
+        assert_eq!(outcome.defender_hp, 40);
+    }
+
+    #[test]
+    fn explode_on_death() {
+        let defender = with_traits(combatant(9, 0, 5), &[Trait::Explode(6)]);
+        let outcome = resolve(&combatant(10, 0, 20), &defender, &Attack::BASIC);
+        assert_eq!(outcome.explosion, 6);
+        assert_eq!(outcome.counter, 0);
+        assert_eq!(outcome.attacker_hp, 14);
+        let outcome = resolve(&combatant(1, 0, 20), &defender, &Attack::BASIC);
+        assert_eq!(outcome.explosion, 0);
+    }
+
+    #[test]
+    fn defeated_defender_does_not_counter() {
+        let outcome = resolve(&combatant(10, 0, 5), &combatant(99, 0, 10), &Attack::BASIC);
+        assert!(outcome.defender_defeated());
+        assert_eq!(outcome.counter, 0);
+        assert_eq!(outcome.attacker_hp, 5);
+        assert!(!outcome.attacker_defeated());
+    }
+}
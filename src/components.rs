@@ -1,8 +1,23 @@
 use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum TileType {
-    Obstacle,
-    Room,
-    Started,
+    Obstacle, // 障碍, 没有内容
+    #[default]
+    Room, // 房间
+    Started,  // 起始方块
+    Corridor, // 走廊, 偶尔有怪物
+    Shrine,   // 神龛, 必定有物品
+    Vault,    // 宝库, 物品多于怪物
+}
+
+// 区域中生成的内容
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ContentKind {
+    Monster,
+    Value,
+    Empty,
+    Trap, // 踏入时受到伤害
+    Shop, // 花费金币购买物品
 }
@@ -0,0 +1,155 @@
+use bevy::prelude::{Component, Resource};
+use serde::{Deserialize, Serialize};
+
+// 状态效果的种类, 数值均为层数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EffectKind {
+    Poison, // 回合开始时损失生命
+    Burn,   // 回合开始时损失生命, 同时降低防御
+    Shield, // 被命中时抵挡伤害, 抵挡多少减少多少层
+    Weaken, // 降低攻击力
+    Haste,  // 每次出手后追加一次普通攻击
+}
+
+impl EffectKind {
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            EffectKind::Poison => "毒",
+            EffectKind::Burn => "灼",
+            EffectKind::Shield => "盾",
+            EffectKind::Weaken => "弱",
+            EffectKind::Haste => "疾",
+        }
+    }
+}
+
+// turns 为剩余回合数, 每回合开始时减一, 归零后移除
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub stacks: i64,
+    pub turns: u32,
+}
+
+impl Effect {
+    pub fn new(kind: EffectKind, stacks: i64, turns: u32) -> Effect {
+        Effect {
+            kind,
+            stacks,
+            turns,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}{}", self.kind.glyph(), self.counter())
+    }
+
+    // 图标旁显示的层数与剩余回合
+    pub fn counter(&self) -> String {
+        format!("{}({})", self.stacks, self.turns)
+    }
+}
+
+/*
+    身上的状态效果, 玩家的作为资源, 怪物的作为组件挂在区域上
+    点击有怪物的 Found 区域为一个回合, 见 update_player_skills_order
+*/
+#[derive(Component, Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusEffects(pub Vec<Effect>);
+
+impl StatusEffects {
+    // 同种效果叠加层数, 剩余回合取较长者
+    pub fn add(&mut self, effect: Effect) {
+        match self.0.iter_mut().find(|e| e.kind == effect.kind) {
+            Some(existing) => {
+                existing.stacks += effect.stacks;
+                existing.turns = existing.turns.max(effect.turns);
+            }
+            None => self.0.push(effect),
+        }
+    }
+
+    pub fn stacks(&self, kind: EffectKind) -> i64 {
+        self.0
+            .iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| e.stacks)
+            .sum()
+    }
+
+    // 回合开始: 结算持续伤害并减少剩余回合, 返回损失的生命
+    pub fn on_turn_start(&mut self) -> i64 {
+        let damage = self.stacks(EffectKind::Poison) + self.stacks(EffectKind::Burn);
+        for effect in self.0.iter_mut() {
+            effect.turns = effect.turns.saturating_sub(1);
+        }
+        self.0.retain(|e| e.turns > 0 && e.stacks > 0);
+        damage
+    }
+
+    // 出手时: 虚弱降低攻击力
+    pub fn on_attack(&self, atk: i64) -> i64 {
+        (atk - self.stacks(EffectKind::Weaken)).max(0)
+    }
+
+    // 疾速时出手后追加的普通攻击次数
+    pub fn extra_hits(&self) -> usize {
+        if self.stacks(EffectKind::Haste) > 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    // 灼烧降低的防御
+    pub fn def_modifier(&self) -> i64 {
+        -self.stacks(EffectKind::Burn)
+    }
+
+    // 被命中时: 护盾抵挡伤害, 返回实际受到的伤害
+    pub fn on_hit(&mut self, damage: i64) -> i64 {
+        let mut damage = damage;
+        for effect in self.0.iter_mut() {
+            if effect.kind == EffectKind::Shield {
+                let absorbed = effect.stacks.min(damage);
+                effect.stacks -= absorbed;
+                damage -= absorbed;
+            }
+        }
+        self.0.retain(|e| e.stacks > 0);
+        damage
+    }
+
+    pub fn label(&self) -> String {
+        self.0
+            .iter()
+            .map(Effect::label)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poison_ticks_on_turn_start_and_expires() {
+        let mut effects = StatusEffects(vec![Effect::new(EffectKind::Poison, 3, 2)]);
+        assert_eq!(effects.on_turn_start(), 3);
+        assert_eq!(effects.on_turn_start(), 3);
+        assert!(effects.0.is_empty());
+        assert_eq!(effects.on_turn_start(), 0);
+    }
+
+    #[test]
+    fn effects_stack() {
+        let mut effects = StatusEffects::default();
+        effects.add(Effect::new(EffectKind::Poison, 2, 3));
+        effects.add(Effect::new(EffectKind::Poison, 1, 1));
+        assert_eq!(
+            effects,
+            StatusEffects(vec![Effect::new(EffectKind::Poison, 3, 3)])
+        );
+    }
+}
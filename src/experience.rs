@@ -0,0 +1,390 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::FontAssets,
+    player::PlayerStatus,
+    player_skill::{PlayerSkills, SkillEvent},
+    rng::{self, RAND, RNG},
+    GameStage,
+};
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(get_experience_config())
+            .init_resource::<PendingLevelUps>()
+            .init_resource::<LevelUpOffer>()
+            .add_system_set(SystemSet::on_enter(GameStage::Main).with_system(reset_level_ups))
+            // 放在 PostUpdate 中, 同一帧里的通关与失败先切换状态
+            .add_system_to_stage(CoreStage::PostUpdate, start_level_up)
+            .add_system_set(SystemSet::on_enter(GameStage::LevelUp).with_system(roll_offer))
+            .add_system_set(
+                SystemSet::on_update(GameStage::LevelUp)
+                    .with_system(update_offer_panel)
+                    .with_system(choose_reward),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameStage::LevelUp).with_system(despawn_offer_panel),
+            );
+    }
+}
+
+// 升级时可选的奖励
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Reward {
+    MaxHp(i64), // 提升生命上限并回复等量生命
+    Atk(i64),
+    Def(i64),
+    Skill(SkillEvent), // 习得新技能
+}
+
+impl Reward {
+    pub fn label(&self) -> String {
+        match self {
+            Reward::MaxHp(n) => format!("生命上限 +{}", n),
+            Reward::Atk(n) => format!("攻击力 +{}", n),
+            Reward::Def(n) => format!("防御力 +{}", n),
+            Reward::Skill(event) => format!("新技能 {}", event.to_skill().name),
+        }
+    }
+
+    pub fn apply(&self, player_status: &mut PlayerStatus, player_skills: &mut PlayerSkills) {
+        match self {
+            Reward::MaxHp(n) => {
+                player_status.max_hp += n;
+                player_status.cur_hp += n;
+            }
+            Reward::Atk(n) => player_status.atk += n,
+            Reward::Def(n) => player_status.def += n,
+            Reward::Skill(event) => player_skills.learn(*event),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Deserialize, Serialize)]
+pub struct ExperienceConfig {
+    pub xp_base: i64,
+    pub xp_step: i64,
+    pub hp_per_level: i64,
+    pub offered: usize,
+    pub rewards: Vec<Reward>,
+}
+
+impl ExperienceConfig {
+    // 从 level 级升到下一级所需的经验
+    pub fn xp_to_next(&self, level: u32) -> i64 {
+        self.xp_base + self.xp_step * level.saturating_sub(1) as i64
+    }
+
+    /*
+        获得经验, 经验足够时连续升级并提升生命上限, 返回升级的次数
+        升级不回复生命, 避免与怪物同归于尽时被救回
+    */
+    pub fn gain_xp(&self, player_status: &mut PlayerStatus, xp: i64) -> u32 {
+        let mut levels = 0;
+        player_status.xp += xp;
+        while player_status.xp >= self.xp_to_next(player_status.level) {
+            player_status.xp -= self.xp_to_next(player_status.level);
+            player_status.level += 1;
+            player_status.max_hp += self.hp_per_level;
+            levels += 1;
+        }
+        levels
+    }
+
+    // 不放回地抽取本次升级可选的奖励, 已学会的技能不再提供
+    pub fn roll(&self, player_skills: &PlayerSkills, rng: &mut RNG) -> Vec<Reward> {
+        let mut candidates = self
+            .rewards
+            .iter()
+            .filter(|reward| match reward {
+                Reward::Skill(event) => !player_skills.has(*event),
+                _ => true,
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let mut offer = vec![];
+        while offer.len() < self.offered && !candidates.is_empty() {
+            let (_, i) = rng.random_range_i64(0, candidates.len() as i64 - 1);
+            offer.push(candidates.remove(i as usize));
+        }
+        offer
+    }
+
+    /*
+        升级所需经验必须为正, 否则 gain_xp 会无限升级
+        至少要有一项非技能奖励, 技能全部学会后仍有可选的奖励
+    */
+    pub fn check(&self) -> Result<(), String> {
+        if self.xp_base <= 0 || self.xp_step < 0 {
+            return Err(format!(
+                "xp_base must > 0 and xp_step must >= 0, got {} and {}",
+                self.xp_base, self.xp_step
+            ));
+        }
+        if self.offered == 0 || self.offered > CHOICE_KEYS.len() {
+            return Err(format!(
+                "offered must in 1..={}, got {}",
+                CHOICE_KEYS.len(),
+                self.offered
+            ));
+        }
+        if self
+            .rewards
+            .iter()
+            .all(|reward| matches!(reward, Reward::Skill(_)))
+        {
+            return Err("rewards must contain at least one non-skill reward".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn get_experience_config() -> ExperienceConfig {
+    let config = include_str!("../assets/config/experience.ron");
+    let config: ExperienceConfig = ron::from_str(config).unwrap();
+    if let Err(err) = config.check() {
+        panic!("invalid experience.ron: {}", err);
+    }
+    config
+}
+
+// 尚未选择奖励的升级次数
+#[derive(Resource, Debug, Default)]
+pub struct PendingLevelUps(pub u32);
+
+// 当前提供选择的奖励
+#[derive(Resource, Debug, Default)]
+struct LevelUpOffer(Vec<Reward>);
+
+#[derive(Component)]
+struct LevelUpPanel;
+
+#[derive(Component)]
+struct RewardButton(usize);
+
+const CHOICE_KEYS: [KeyCode; 3] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
+fn reset_level_ups(mut pending: ResMut<PendingLevelUps>) {
+    pending.0 = 0;
+}
+
+// 有待选择的升级时暂停地图, 进入选择界面
+fn start_level_up(
+    pending: Res<PendingLevelUps>,
+    player_status: Res<PlayerStatus>,
+    mut game_stage: ResMut<State<GameStage>>,
+) {
+    if pending.0 > 0 && player_status.cur_hp > 0 && game_stage.current() == &GameStage::Main {
+        // 已有状态切换在排队时(通关或失败)不进入选择界面
+        let _ = game_stage.push(GameStage::LevelUp);
+    }
+}
+
+fn roll_offer(
+    config: Res<ExperienceConfig>,
+    player_skills: Res<PlayerSkills>,
+    mut offer: ResMut<LevelUpOffer>,
+) {
+    offer.0 = config.roll(&player_skills, RAND.lock().unwrap().stream(rng::GROWTH));
+}
+
+fn update_offer_panel(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    offer: Res<LevelUpOffer>,
+    player_status: Res<PlayerStatus>,
+    panel_query: Query<Entity, With<LevelUpPanel>>,
+) {
+    if !offer.is_changed() {
+        return;
+    }
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let title_style = TextStyle {
+        font: font_assets.hanti.clone_weak(),
+        font_size: 40.,
+        color: Color::GOLD,
+    };
+    let btn_style = TextStyle {
+        font: font_assets.hanti.clone_weak(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba_u8(0, 0, 0, 192).into(),
+            ..default()
+        })
+        .insert(LevelUpPanel)
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Px(10.)),
+                    ..default()
+                },
+                text: Text::from_section(
+                    format!("升级! Lv {}", player_status.level),
+                    title_style.clone(),
+                ),
+                ..default()
+            });
+            for (i, reward) in offer.0.iter().enumerate() {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(240.), Val::Px(40.)),
+                            margin: UiRect::all(Val::Px(5.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::rgb_u8(64, 64, 64).into(),
+                        ..default()
+                    })
+                    .insert(RewardButton(i))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: Text::from_section(
+                                format!("{}. {}", i + 1, reward.label()),
+                                btn_style.clone(),
+                            ),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+/*
+    按数字键或点击选择奖励
+    还有待选择的升级时重新抽取, 否则回到地图
+    选择后清除本帧的按键与点击, 避免 pop 后同一帧的地图系统再次响应
+*/
+fn choose_reward(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+    interaction_query: Query<(&Interaction, &RewardButton), Changed<Interaction>>,
+    config: Res<ExperienceConfig>,
+    mut offer: ResMut<LevelUpOffer>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut player_skills: ResMut<PlayerSkills>,
+    mut game_stage: ResMut<State<GameStage>>,
+) {
+    let mut chosen = CHOICE_KEYS.iter().position(|key| keys.just_pressed(*key));
+    for (interaction, RewardButton(i)) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            chosen = Some(*i);
+        }
+    }
+    let reward = match chosen.and_then(|i| offer.0.get(i)) {
+        Some(reward) => *reward,
+        None => return,
+    };
+
+    for key in CHOICE_KEYS {
+        keys.clear_just_pressed(key);
+    }
+    mouse.clear_just_pressed(MouseButton::Left);
+
+    reward.apply(&mut player_status, &mut player_skills);
+    pending.0 = pending.0.saturating_sub(1);
+    if pending.0 > 0 {
+        offer.0 = config.roll(&player_skills, RAND.lock().unwrap().stream(rng::GROWTH));
+    } else {
+        game_stage.pop().unwrap();
+    }
+}
+
+fn despawn_offer_panel(mut commands: Commands, panel_query: Query<Entity, With<LevelUpPanel>>) {
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rewards: Vec<Reward>) -> ExperienceConfig {
+        ExperienceConfig {
+            xp_base: 10,
+            xp_step: 10,
+            hp_per_level: 10,
+            offered: 3,
+            rewards,
+        }
+    }
+
+    #[test]
+    fn bundled_config_is_valid() {
+        assert_eq!(get_experience_config().check(), Ok(()));
+    }
+
+    #[test]
+    fn gain_xp_levels_up_repeatedly_and_keeps_leftover() {
+        let config = config(vec![Reward::Atk(1)]);
+        let mut player_status = PlayerStatus::default();
+        // 1 -> 2 需要 10, 2 -> 3 需要 20, 剩余 5
+        assert_eq!(config.gain_xp(&mut player_status, 35), 2);
+        assert_eq!(player_status.level, 3);
+        assert_eq!(player_status.xp, 5);
+        assert_eq!(player_status.max_hp, 120);
+        assert_eq!(player_status.cur_hp, 100);
+
+        // 3 -> 4 需要 30, 经验不足时不升级
+        assert_eq!(config.gain_xp(&mut player_status, 24), 0);
+        assert_eq!(player_status.level, 3);
+        assert_eq!(player_status.xp, 29);
+    }
+
+    #[test]
+    fn roll_never_offers_known_skills() {
+        let config = config(vec![
+            Reward::Atk(3),
+            Reward::Skill(SkillEvent::DoubleAttack),
+            Reward::Skill(SkillEvent::HeavyAttack),
+            Reward::Skill(SkillEvent::PiercingAttack),
+        ]);
+        let mut player_skills = PlayerSkills::default();
+        player_skills.learn(SkillEvent::HeavyAttack);
+        for seed in 0..50 {
+            let offer = config.roll(&player_skills, &mut RNG::new(seed));
+            assert_eq!(offer.len(), 2);
+            assert!(offer.contains(&Reward::Atk(3)));
+            assert!(offer.contains(&Reward::Skill(SkillEvent::PiercingAttack)));
+        }
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let mut bad = config(vec![Reward::Atk(1)]);
+        bad.xp_base = 0;
+        assert!(bad.check().is_err());
+
+        let mut bad = config(vec![Reward::Atk(1)]);
+        bad.xp_step = -1;
+        assert!(bad.check().is_err());
+
+        let mut bad = config(vec![Reward::Atk(1)]);
+        bad.offered = 0;
+        assert!(bad.check().is_err());
+
+        let bad = config(vec![Reward::Skill(SkillEvent::HeavyAttack)]);
+        assert!(bad.check().is_err());
+    }
+}
@@ -1,16 +1,33 @@
 use bevy::prelude::*;
 
-use crate::GameStage;
+use crate::{rng::RunSeed, GameStage};
 pub struct GameOverPlugin;
 
 impl Plugin for GameOverPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameStage::GameOver).with_system(cleanup))
-            .add_system_set(SystemSet::on_update(GameStage::GameOver).with_system(again_system));
+        app.add_system_set(SystemSet::on_enter(GameStage::GameOver).with_system(game_over))
+            .add_system_set(SystemSet::on_update(GameStage::GameOver).with_system(again_system))
+            .add_system_set(SystemSet::on_enter(GameStage::Victory).with_system(victory))
+            .add_system_set(SystemSet::on_update(GameStage::Victory).with_system(again_system));
     }
 }
 
-fn cleanup(asset_server: Res<AssetServer>, query: Query<Entity>, mut commands: Commands) {
+fn game_over(asset_server: Res<AssetServer>, query: Query<Entity>, commands: Commands) {
+    cleanup(asset_server, query, commands, "Game Over", Color::RED);
+}
+
+fn victory(asset_server: Res<AssetServer>, query: Query<Entity>, commands: Commands) {
+    cleanup(asset_server, query, commands, "通关", Color::GOLD);
+}
+
+// 清空场景并显示结局标题与重开按钮
+fn cleanup(
+    asset_server: Res<AssetServer>,
+    query: Query<Entity>,
+    mut commands: Commands,
+    title: &str,
+    color: Color,
+) {
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
@@ -19,7 +36,7 @@ fn cleanup(asset_server: Res<AssetServer>, query: Query<Entity>, mut commands: C
     let style = TextStyle {
         font: asset_server.load("fonts/hanti.ttf"),
         font_size: 120.0,
-        color: Color::RED,
+        color,
     };
 
     let btn_style = TextStyle {
@@ -77,7 +94,7 @@ fn cleanup(asset_server: Res<AssetServer>, query: Query<Entity>, mut commands: C
                 },
                 text: Text {
                     sections: vec![TextSection {
-                        value: "Game Over".to_string(),
+                        value: title.to_string(),
                         style: style.clone(),
                         ..Default::default()
                     }],
@@ -96,6 +113,7 @@ fn again_system(
     query: Query<Entity>,
     mut interaction_query: Query<&Interaction, (With<Interaction>, With<Button>)>,
     mut game_stage: ResMut<State<GameStage>>,
+    mut run_seed: ResMut<RunSeed>,
     mut commands: Commands,
 ) {
     for interaction in interaction_query.iter_mut() {
@@ -104,6 +122,7 @@ fn again_system(
                 for entity in query.iter() {
                     commands.entity(entity).despawn();
                 }
+                *run_seed = run_seed.next_run();
                 game_stage.set(GameStage::Main).unwrap();
             }
             _ => (),
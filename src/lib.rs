@@ -4,7 +4,10 @@ pub extern crate bevy;
 pub extern crate serde;
 
 mod camera;
+pub mod combat;
 pub mod components;
+pub mod effects;
+mod experience;
 // mod debugger;
 mod assets;
 mod game_over;
@@ -22,6 +25,7 @@ use bevy::{diagnostic::LogDiagnosticsPlugin, prelude::*};
 use bevy_kira_audio::AudioPlugin;
 use bevy_ninepatch::*;
 pub use camera::CameraPlugin;
+use experience::ExperiencePlugin;
 use game_over::GameOverPlugin;
 use panel::PanelPlugin;
 pub use player::PlayerStatusType;
@@ -29,7 +33,8 @@ pub use player::PlayerStatusType;
 use bevy_embedded_assets::EmbeddedAssetPlugin;
 use player::PlayerPlugin;
 use player_skill::PlayerSkillPlugin;
-use regions::RegionPlugin;
+use regions::{RegionPlugin, SelectedLevel};
+use rng::RunSeed;
 
 use bevy_asset_loader::prelude::*;
 
@@ -40,6 +45,8 @@ pub enum GameStage {
     Loading,
     Main,
     GameOver,
+    Victory,
+    LevelUp, // 压在 Main 之上, 选择升级奖励时暂停地图
 }
 
 impl Plugin for GamePlugin {
@@ -53,6 +60,8 @@ impl Plugin for GamePlugin {
                 .with_collection::<UIImageAssets>(),
         )
         .add_state(GameStage::Loading)
+        .insert_resource(RunSeed::from_args())
+        .insert_resource(SelectedLevel::from_args())
         .add_plugin(NinePatchPlugin::<()>::default())
         .add_plugin(PanelPlugin)
         .add_plugin(GameOverPlugin)
@@ -60,7 +69,8 @@ impl Plugin for GamePlugin {
         .add_plugin(AudioPlugin)
         .add_plugin(RegionPlugin)
         .add_plugin(PlayerPlugin)
-        .add_plugin(PlayerSkillPlugin);
+        .add_plugin(PlayerSkillPlugin)
+        .add_plugin(ExperiencePlugin);
     }
 }
 
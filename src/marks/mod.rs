@@ -1,5 +1,8 @@
 use bevy::prelude::Component;
 
+use crate::combat::{Combatant, Trait};
+use crate::effects::StatusEffects;
+
 pub mod region_status;
 pub mod region_type;
 
@@ -20,8 +23,42 @@ pub struct EnemyText;
 #[derive(Component, Clone)]
 pub struct ValueText;
 
+// 陷阱, 踏入时受到的伤害
+#[derive(Component, Clone, Copy)]
+pub struct Trap {
+    pub damage: i64,
+}
+
+// 商店, 购买其中物品的价格
+#[derive(Component, Clone, Copy)]
+pub struct Shop {
+    pub price: i64,
+}
+
+#[derive(Component, Clone)]
+pub struct TrapText;
+#[derive(Component, Clone)]
+pub struct ShopText;
+
+// 区域上的危险提示数字, 指向所属区域的id
+#[derive(Component, Clone, Copy)]
+pub struct HintText(pub u64);
+
+// 区域上玩家标记的文字, 指向所属区域的id
+#[derive(Component, Clone, Copy)]
+pub struct FlagText(pub u64);
+
+// 通往下一层的楼梯
+#[derive(Component, Clone, Copy)]
+pub struct StairsMark;
+#[derive(Component, Clone)]
+pub struct StairsText;
+
 #[derive(Component)]
 pub struct EnemyMark;
+// 首领, 击败后通关
+#[derive(Component, Clone, Copy)]
+pub struct BossMark;
 #[derive(Component, Clone)]
 pub struct EnemyStatus {
     pub name: String,
@@ -29,6 +66,22 @@ pub struct EnemyStatus {
     pub def: i64,
     pub max_hp: u64,
     pub cur_hp: i64,
+    pub traits: Vec<Trait>,
+    pub xp: i64,   // 击败后获得的经验
+    pub gold: i64, // 击败后获得的金币
+}
+
+impl EnemyStatus {
+    pub fn to_combatant(&self, effects: &StatusEffects) -> Combatant {
+        Combatant {
+            atk: self.atk,
+            def: self.def,
+            hp: self.cur_hp,
+            max_hp: self.max_hp as i64,
+            traits: self.traits.clone(),
+            effects: effects.clone(),
+        }
+    }
 }
 
 #[derive(Component, Clone)]
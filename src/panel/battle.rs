@@ -2,8 +2,12 @@ use bevy::{prelude::*, sprite::Anchor};
 
 use crate::{
     assets::UIImageAssets,
+    combat::{self, Forecast, MAX_EXCHANGES},
+    effects::StatusEffects,
     marks::{EnemyLabel, EnemyStatus},
     panel::BattlePanelVisibly,
+    player::PlayerStatus,
+    player_skill::PlayerSkills,
     pool::values,
     regions::{CurrentOverRegion, RegionEntityMap, RegionMark},
     GameStage, PlayerStatusType,
@@ -16,7 +20,9 @@ impl Plugin for BattlePlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(GameStage::Main).with_system(setup))
             .add_system_set(
-                SystemSet::on_update(GameStage::Main).with_system(update_battle_panel_enemy_label),
+                SystemSet::on_update(GameStage::Main)
+                    .with_system(update_battle_panel_enemy_label)
+                    .with_system(update_battle_panel_effects),
             );
     }
 }
@@ -27,6 +33,38 @@ enum BattlePanelPart {
     EnemyHP,
     EnemyDEF,
     EnemyATK,
+    EnemyTraits,  // 怪物的特性
+    Forecast,     // 各回合伤害的范围与击倒所需回合
+    ForecastLoss, // 预计损失的生命, 致命时显示警告
+}
+
+// 状态效果图标所在的行, 图标在 update_battle_panel_effects 中生成
+#[derive(Component, Clone, Copy)]
+enum EffectsRow {
+    Player,
+    Enemy,
+}
+
+// 预测的两行文字, 致命时第二行为红色
+fn forecast_text(forecast: &Forecast) -> (String, String, Color) {
+    let exchanges = match forecast.exchanges {
+        Some(n) => format!("{} 回合", n),
+        None => "打不倒".to_string(),
+    };
+    let damage = match forecast.damage_range() {
+        Some((min, max)) if min != max => format!("伤害 {}~{} / {}", min, max, exchanges),
+        Some((damage, _)) => format!("伤害 {} / {}", damage, exchanges),
+        None => format!("伤害 - / {}", exchanges),
+    };
+    if forecast.lethal {
+        (damage, "致命! 战斗中会倒下".to_string(), Color::RED)
+    } else {
+        (
+            damage,
+            format!("损失 HP {}", forecast.hp_lost),
+            Color::WHITE,
+        )
+    }
 }
 
 fn setup(
@@ -172,6 +210,70 @@ fn setup(
                 })
                 .insert(BattlePanelPart::EnemyDEF)
                 .insert(BattlePanelVisibly);
+
+            parent
+                .spawn(Text2dBundle {
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: "".to_string(),
+                            style: TextStyle {
+                                font_size: 14.0,
+                                ..with_color(Color::ORANGE)
+                            },
+                        }],
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::new(146.0, -16.0, 1.0)),
+                    ..default()
+                })
+                .insert(BattlePanelPart::EnemyTraits)
+                .insert(BattlePanelVisibly);
+
+            // effects
+            parent
+                .spawn(SpatialBundle::from_transform(Transform::from_translation(
+                    Vec3::new(140.0, -34.0, 1.0),
+                )))
+                .insert(EffectsRow::Enemy)
+                .insert(BattlePanelVisibly);
+
+            parent
+                .spawn(SpatialBundle::from_transform(Transform::from_translation(
+                    Vec3::new(96.0, -99.0, 1.0),
+                )))
+                .insert(EffectsRow::Player)
+                .insert(BattlePanelVisibly);
+
+            // forecast
+            parent
+                .spawn(Text2dBundle {
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: "".to_string(),
+                            style: with_color(Color::WHITE),
+                        }],
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::new(104.0, -62.0, 1.0)),
+                    ..default()
+                })
+                .insert(BattlePanelPart::Forecast)
+                .insert(BattlePanelVisibly);
+
+            parent
+                .spawn(Text2dBundle {
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: "".to_string(),
+                            style: with_color(Color::WHITE),
+                        }],
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::new(104.0, -82.0, 1.0)),
+                    ..default()
+                })
+                .insert(BattlePanelPart::ForecastLoss)
+                .insert(BattlePanelVisibly);
         });
 }
 
@@ -185,6 +287,10 @@ fn update_battle_panel_enemy_label(
     region_entity_map: Res<RegionEntityMap>,
     current_over_region: Res<CurrentOverRegion>,
     asset_server: Res<AssetServer>,
+    player_status: Res<PlayerStatus>,
+    player_effects: Res<StatusEffects>,
+    player_skills: Res<PlayerSkills>,
+    effects_query: Query<&StatusEffects>,
 ) {
     match current_over_region.as_ref() {
         CurrentOverRegion::None => (),
@@ -199,7 +305,17 @@ fn update_battle_panel_enemy_label(
                         .unwrap();
                 }
                 if let Ok(status) = region_mark_query.get_component::<EnemyStatus>(*entity) {
+                    let effects = effects_query.get(*entity).cloned().unwrap_or_default();
+                    let forecast = combat::forecast(
+                        &player_status.to_combatant(&player_effects),
+                        &status.to_combatant(&effects),
+                        &player_skills.upcoming_attacks(MAX_EXCHANGES),
+                    );
+                    let (damage_text, loss_text, loss_color) = forecast_text(&forecast);
                     for (mut text, part) in panel_parts_text_query.iter_mut() {
+                        if let BattlePanelPart::ForecastLoss = part {
+                            text.sections[0].style.color = loss_color;
+                        }
                         text.sections[0].value = match part {
                             BattlePanelPart::EnemyIcon => "".to_string(),
                             BattlePanelPart::EnemyHP => {
@@ -207,6 +323,14 @@ fn update_battle_panel_enemy_label(
                             }
                             BattlePanelPart::EnemyDEF => format!("{}", status.def),
                             BattlePanelPart::EnemyATK => format!("{}", status.atk),
+                            BattlePanelPart::EnemyTraits => status
+                                .traits
+                                .iter()
+                                .map(|t| t.label())
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                            BattlePanelPart::Forecast => damage_text.clone(),
+                            BattlePanelPart::ForecastLoss => loss_text.clone(),
                         };
                     }
                 }
@@ -214,3 +338,70 @@ fn update_battle_panel_enemy_label(
         }
     };
 }
+
+/*
+    玩家与当前怪物身上的状态效果, 每个效果为图标加层数
+    只在显示的效果变化时重新生成图标
+*/
+fn update_battle_panel_effects(
+    mut commands: Commands,
+    ui_image_assets: Res<UIImageAssets>,
+    asset_server: Res<AssetServer>,
+    current_over_region: Res<CurrentOverRegion>,
+    region_entity_map: Res<RegionEntityMap>,
+    player_effects: Res<StatusEffects>,
+    effects_query: Query<&StatusEffects, With<RegionMark>>,
+    row_query: Query<(Entity, &EffectsRow)>,
+    mut shown: Local<(StatusEffects, StatusEffects)>,
+) {
+    let enemy_effects = match current_over_region.as_ref() {
+        CurrentOverRegion::Region(id) => region_entity_map
+            .0
+            .get(id)
+            .and_then(|entity| effects_query.get(*entity).ok())
+            .cloned()
+            .unwrap_or_default(),
+        CurrentOverRegion::None => StatusEffects::default(),
+    };
+    if shown.0 == *player_effects && shown.1 == enemy_effects {
+        return;
+    }
+    *shown = (player_effects.clone(), enemy_effects);
+
+    let font = asset_server.load("fonts/hanti.ttf");
+    for (entity, row) in row_query.iter() {
+        let effects = match row {
+            EffectsRow::Player => &shown.0,
+            EffectsRow::Enemy => &shown.1,
+        };
+        commands.entity(entity).despawn_descendants();
+        commands.entity(entity).with_children(|parent| {
+            for (i, effect) in effects.0.iter().enumerate() {
+                let x = i as f32 * 28.0;
+                parent.spawn(SpriteBundle {
+                    texture: ui_image_assets.effect_icon(effect.kind),
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(12.0, 12.0)),
+                        anchor: Anchor::TopLeft,
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::new(x, 0.0, 0.0)),
+                    ..default()
+                });
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        effect.stacks.to_string(),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 12.0,
+                            color: Color::ORANGE,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER_LEFT),
+                    transform: Transform::from_translation(Vec3::new(x + 13.0, -6.0, 0.0)),
+                    ..default()
+                });
+            }
+        });
+    }
+}
@@ -106,6 +106,9 @@ fn update_value_content(
                                     format!("\n防御力 {}", v.to_str())
                                 }
                                 values::KeyValue::PlayerGold(v) => format!("\n魂 {}", v.to_str()),
+                                values::KeyValue::PlayerEffect(e) => {
+                                    format!("\n{} {}层 {}回合", e.kind.glyph(), e.stacks, e.turns)
+                                }
                             })
                             .collect::<Vec<_>>()
                             .join("\n");
@@ -3,11 +3,15 @@ use bevy_ninepatch::{NinePatchBuilder, NinePatchBundle, NinePatchData};
 
 use crate::{
     assets::UIImageAssets,
+    combat::Combatant,
+    effects::StatusEffects,
+    experience::ExperienceConfig,
     marks::RegionStatus,
     regions::{
         events::{MouseOverEmpty, MouseOverRegionEvent},
-        CurrentOverRegion, RegionEntityMap, RegionMark,
+        CurrentFloor, CurrentOverRegion, RegionEntityMap, RegionMark,
     },
+    rng::RunSeed,
     GameStage,
 };
 
@@ -16,6 +20,7 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.init_resource::<PlayerStatus>()
+            .init_resource::<StatusEffects>()
             .add_system_set(SystemSet::on_enter(GameStage::Main).with_system(setup))
             .add_system_set(
                 SystemSet::on_update(GameStage::Main)
@@ -25,6 +30,8 @@ impl Plugin for PlayerPlugin {
                     // .with_system(update_intro_panel_with_value)
                     // .with_system(update_intro_panel_with_enemy)
                     .with_system(update_player_status)
+                    .with_system(update_floor_text)
+                    .with_system(update_effects_bar)
                     .with_system(to_game_over),
             );
     }
@@ -37,6 +44,21 @@ pub struct PlayerStatus {
     pub cur_hp: i64,
     pub max_hp: i64,
     pub gold: i64,
+    pub level: u32,
+    pub xp: i64, // 当前等级已获得的经验
+}
+
+impl PlayerStatus {
+    pub fn to_combatant(&self, effects: &StatusEffects) -> Combatant {
+        Combatant {
+            atk: self.atk,
+            def: self.def,
+            hp: self.cur_hp,
+            max_hp: self.max_hp,
+            traits: vec![],
+            effects: effects.clone(),
+        }
+    }
 }
 
 impl Default for PlayerStatus {
@@ -47,6 +69,8 @@ impl Default for PlayerStatus {
             cur_hp: 100,
             max_hp: 100,
             gold: 10,
+            level: 1,
+            xp: 0,
         }
     }
 }
@@ -57,22 +81,31 @@ struct PlayerStatusHub;
 #[derive(Component)]
 struct IntroPanel;
 
+#[derive(Component)]
+struct FloorText;
+
+#[derive(Component)]
+struct EffectsBar;
+
 #[derive(Component)]
 pub enum PlayerStatusType {
     ATK,
     DEF,
     HP,
     GOLD,
+    XP,
 }
 
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     ui_image: Res<UIImageAssets>,
+    run_seed: Res<RunSeed>,
     mut nine_patches: ResMut<Assets<NinePatchBuilder<()>>>,
 ) {
     // UI camera
     commands.insert_resource(PlayerStatus::default());
+    commands.insert_resource(StatusEffects::default());
     // player status hub
     // TODO 加底图
     let nine_patch_handle = nine_patches.add(NinePatchBuilder::by_margins(10, 10, 20, 20));
@@ -256,6 +289,83 @@ fn setup(
                         })
                         .insert(PlayerStatusType::GOLD);
                 });
+
+            // 等级与经验
+            parent
+                .spawn(TextBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Px(16.0)),
+                        min_size: Size::new(Val::Px(80.0), Val::Px(16.0)),
+                        ..default()
+                    },
+                    text: Text {
+                        sections: vec![TextSection {
+                            style: TextStyle {
+                                font: asset_server.load("fonts/hanti.ttf"),
+                                font_size: 16.0,
+                                color: Color::PURPLE.into(),
+                                ..default()
+                            },
+                            value: "Lv 1 0/10".to_string(),
+                        }],
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(PlayerStatusType::XP);
+
+            // floor
+            parent
+                .spawn(TextBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Px(16.0)),
+                        ..default()
+                    },
+                    text: Text {
+                        sections: vec![TextSection {
+                            style: TextStyle {
+                                font: asset_server.load("fonts/hanti.ttf"),
+                                font_size: 16.0,
+                                color: Color::WHITE.into(),
+                                ..default()
+                            },
+                            value: "".to_string(),
+                        }],
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(FloorText);
+
+            // 玩家身上的状态效果, 图标在 update_effects_bar 中生成
+            parent
+                .spawn(NodeBundle {
+                    background_color: Color::NONE.into(),
+                    style: Style { ..default() },
+                    ..default()
+                })
+                .insert(EffectsBar);
+
+            // seed
+            parent.spawn(TextBundle {
+                style: Style {
+                    size: Size::new(Val::Auto, Val::Px(16.0)),
+                    ..default()
+                },
+                text: Text {
+                    sections: vec![TextSection {
+                        style: TextStyle {
+                            font: asset_server.load("fonts/hanti.ttf"),
+                            font_size: 16.0,
+                            color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        value: format!("种子 {}", run_seed.seed),
+                    }],
+                    ..default()
+                },
+                ..default()
+            });
         })
         .id();
 
@@ -302,6 +412,7 @@ fn setup(
 
 fn update_player_status(
     player_status: Res<PlayerStatus>,
+    experience_config: Res<ExperienceConfig>,
     mut query_text: Query<(&mut Text, &PlayerStatusType)>,
 ) {
     for (mut text, status) in query_text.iter_mut() {
@@ -310,10 +421,78 @@ fn update_player_status(
             PlayerStatusType::DEF => format!("{}", player_status.def),
             PlayerStatusType::HP => format!("{}/{}", player_status.cur_hp, player_status.max_hp),
             PlayerStatusType::GOLD => format!("{}", player_status.gold),
+            PlayerStatusType::XP => format!(
+                "Lv {} {}/{}",
+                player_status.level,
+                player_status.xp,
+                experience_config.xp_to_next(player_status.level)
+            ),
         };
     }
 }
 
+fn update_effects_bar(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ui_image: Res<UIImageAssets>,
+    effects: Res<StatusEffects>,
+    bar_query: Query<Entity, With<EffectsBar>>,
+) {
+    if !effects.is_changed() {
+        return;
+    }
+    for bar in bar_query.iter() {
+        commands.entity(bar).despawn_descendants();
+        commands.entity(bar).with_children(|parent| {
+            for effect in effects.0.iter() {
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(16.0), Val::Px(16.0)),
+                        margin: UiRect {
+                            right: Val::Px(2.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    image: UiImage(ui_image.effect_icon(effect.kind)),
+                    ..default()
+                });
+                parent.spawn(TextBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Px(16.0)),
+                        margin: UiRect {
+                            right: Val::Px(6.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    text: Text::from_section(
+                        effect.counter(),
+                        TextStyle {
+                            font: asset_server.load("fonts/hanti.ttf"),
+                            font_size: 16.0,
+                            color: Color::ORANGE,
+                        },
+                    ),
+                    ..default()
+                });
+            }
+        });
+    }
+}
+
+fn update_floor_text(
+    current_floor: Res<CurrentFloor>,
+    mut query_text: Query<&mut Text, With<FloorText>>,
+) {
+    for mut text in query_text.iter_mut() {
+        let value = format!("第 {} 层", current_floor.0);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
 fn update_current_over_region(
     mut mouse_over_region: EventReader<MouseOverRegionEvent>,
     region_query: Query<&RegionStatus, With<RegionMark>>,
@@ -1,14 +1,17 @@
-use std::collections::LinkedList;
+use std::collections::{HashMap, LinkedList};
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     assets::{FontAssets, UIImageAssets},
-    marks::EnemyStatus,
+    combat::{self, Attack, DamageType},
+    effects::StatusEffects,
+    marks::{EnemyStatus, RegionId, RegionStatus},
     player::PlayerStatus,
     regions::{
         events::{AtkMonsterWithPlayerSkill, AudioSound, PlayAudioEvent},
-        ChangeEnemyHpEvent, RegionEntityMap,
+        ChangeEnemyHpEvent, RegionEntityMap, Regions,
     },
     GameStage,
 };
@@ -17,11 +20,13 @@ pub struct PlayerSkillPlugin;
 
 impl Plugin for PlayerSkillPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PlayerSkills>().add_system_set(
-            SystemSet::on_update(GameStage::Main)
-                .with_system(update_player_skill)
-                .with_system(update_player_skills_order),
-        );
+        app.init_resource::<PlayerSkills>()
+            .add_system_set(SystemSet::on_enter(GameStage::Main).with_system(reset_player_skills))
+            .add_system_set(
+                SystemSet::on_update(GameStage::Main)
+                    .with_system(update_player_skill)
+                    .with_system(update_player_skills_order),
+            );
     }
 }
 
@@ -43,10 +48,70 @@ pub struct Skill {
     cd_timer: u32, // 剩余冷却时间
 }
 
-#[derive(Clone)]
-enum SkillEvent {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SkillEvent {
     CommonAttack,
     DoubleAttack,
+    PiercingAttack, // 升级时习得
+    HeavyAttack,    // 升级时习得
+}
+
+impl SkillEvent {
+    fn to_attack(self) -> Attack {
+        match self {
+            SkillEvent::CommonAttack => Attack::BASIC,
+            SkillEvent::DoubleAttack => Attack {
+                multiplier: 2,
+                damage_type: DamageType::Physical,
+            },
+            SkillEvent::PiercingAttack => Attack {
+                multiplier: 1,
+                damage_type: DamageType::True,
+            },
+            SkillEvent::HeavyAttack => Attack {
+                multiplier: 3,
+                damage_type: DamageType::Physical,
+            },
+        }
+    }
+
+    // 刚获得的技能处于冷却中
+    pub fn to_skill(self) -> Skill {
+        let (name, description, icon, cd) = match self {
+            SkillEvent::CommonAttack => (
+                "普通攻击",
+                "朴实无华的一次攻击, 造成等同于攻击力的伤害",
+                "textures/ui/skill_icons/skill_icon1.png",
+                0,
+            ),
+            SkillEvent::DoubleAttack => (
+                "会心攻击",
+                "认真的一次攻击, 造成等同于攻击力2倍的伤害",
+                "textures/ui/skill_icons/skill_icon2.png",
+                1,
+            ),
+            SkillEvent::PiercingAttack => (
+                "破甲攻击",
+                "无视防御的一次攻击, 造成等同于攻击力的伤害",
+                "textures/ui/skill_icons/skill_icon1.png",
+                2,
+            ),
+            SkillEvent::HeavyAttack => (
+                "重击",
+                "蓄力的一次攻击, 造成等同于攻击力3倍的伤害",
+                "textures/ui/skill_icons/skill_icon2.png",
+                3,
+            ),
+        };
+        Skill {
+            name: name.to_string(),
+            description: description.to_string(),
+            icon: icon.to_string(),
+            event: self,
+            cd,
+            cd_timer: cd,
+        }
+    }
 }
 
 impl Default for PlayerSkills {
@@ -54,26 +119,63 @@ impl Default for PlayerSkills {
         let mut player_skills = Self {
             skills: Default::default(),
         };
-        player_skills.skills.push_back(Skill {
-            name: "普通攻击".to_string(),
-            description: "朴实无华的一次攻击, 造成等同于攻击力的伤害".to_string(),
-            icon: "textures/ui/skill_icons/skill_icon1.png".to_string(),
-            event: SkillEvent::CommonAttack,
-            cd: 0,
-            cd_timer: 0,
-        });
-        player_skills.skills.push_back(Skill {
-            name: "会心攻击".to_string(),
-            description: "认真的一次攻击, 造成等同于攻击力2倍的伤害".to_string(),
-            icon: "textures/ui/skill_icons/skill_icon2.png".to_string(),
-            event: SkillEvent::DoubleAttack,
-            cd: 1,
-            cd_timer: 1,
-        });
+        player_skills.learn(SkillEvent::CommonAttack);
+        player_skills.learn(SkillEvent::DoubleAttack);
         player_skills
     }
 }
 
+impl PlayerSkills {
+    pub fn has(&self, event: SkillEvent) -> bool {
+        self.skills.iter().any(|skill| skill.event == event)
+    }
+
+    // 新技能加入轮转的末尾
+    pub fn learn(&mut self, event: SkillEvent) {
+        self.skills.push_back(event.to_skill());
+    }
+
+    /*
+        取出轮到的技能并推进轮转, 技能进入冷却后放到末尾
+        排在前面仍在冷却的技能会被跳过并减少一次冷却
+    */
+    fn next_skill(&mut self) -> Option<Skill> {
+        let mut triggered = None;
+        while let Some(mut skill) = self.skills.pop_front() {
+            if skill.cd_timer == 0 {
+                if triggered.is_none() {
+                    skill.cd_timer = skill.cd;
+                    self.skills.push_back(skill.clone());
+                    triggered = Some(skill);
+                } else {
+                    self.skills.push_front(skill);
+                    break;
+                }
+            } else {
+                skill.cd_timer -= 1;
+                self.skills.push_back(skill);
+            }
+        }
+        triggered
+    }
+
+    // 接下来 n 次攻击依次使用的招式, 不改变当前的轮转
+    pub fn upcoming_attacks(&self, n: usize) -> Vec<Attack> {
+        let mut skills = PlayerSkills {
+            skills: self.skills.clone(),
+        };
+        (0..n)
+            .map_while(|_| skills.next_skill())
+            .map(|skill| skill.event.to_attack())
+            .collect()
+    }
+}
+
+// 每局开始时只会基础的两个技能
+fn reset_player_skills(mut commands: Commands) {
+    commands.insert_resource(PlayerSkills::default());
+}
+
 pub fn update_player_skill(
     mut commands: Commands,
     self_query: Query<Entity, With<SkillPanelPart>>,
@@ -201,56 +303,72 @@ pub fn update_player_skill(
     }
 }
 
+/*
+    atk_monster 只为点击有怪物的 Found 区域发送 AtkMonsterWithPlayerSkill, 每个事件为一个回合
+    回合开始时玩家与所有怪物先结算状态效果, 再与点击的怪物交战
+*/
 pub fn update_player_skills_order(
     mut atk_monster_with_player_skill: EventReader<AtkMonsterWithPlayerSkill>,
     mut player_skills: ResMut<PlayerSkills>,
 
     mut player_status: ResMut<PlayerStatus>,
-    query: Query<&EnemyStatus>,
+    mut player_effects: ResMut<StatusEffects>,
+    mut enemy_query: Query<(&RegionId, &EnemyStatus, &mut StatusEffects)>,
+    status_query: Query<&RegionStatus>,
     // mut trigger_region_event: EventReader<RegionClickEvent>,
     region_entity_map: Res<RegionEntityMap>,
+    regions: Res<Regions>,
     mut change_enemy_hp_event: EventWriter<ChangeEnemyHpEvent>,
     mut play_audio_event: EventWriter<PlayAudioEvent>,
 ) {
     for AtkMonsterWithPlayerSkill(id) in atk_monster_with_player_skill.iter() {
-        let mut triggered = false;
-        loop {
-            if let Some(mut skill) = player_skills.skills.pop_front() {
-                let skill = &mut skill;
-                if skill.cd_timer == 0 {
-                    if triggered == false {
-                        if let Some(en) = region_entity_map.0.get(id) {
-                            if let Ok(enemy) = query.get_component::<EnemyStatus>(*en) {
-                                match skill.event {
-                                    SkillEvent::CommonAttack => {
-                                        change_enemy_hp_event.send(ChangeEnemyHpEvent(
-                                            *id,
-                                            -(player_status.atk - enemy.def),
-                                        ));
-                                    }
-                                    SkillEvent::DoubleAttack => {
-                                        change_enemy_hp_event.send(ChangeEnemyHpEvent(
-                                            *id,
-                                            -(player_status.atk * 2 - enemy.def),
-                                        ));
-                                    }
-                                };
-                                player_status.cur_hp -= (enemy.atk - player_status.def).max(0);
+        if let Some(skill) = player_skills.next_skill() {
+            player_status.cur_hp -= player_effects.on_turn_start();
+            let mut turn_damage = HashMap::new();
+            for (RegionId(region_id), enemy, mut effects) in enemy_query.iter_mut() {
+                if enemy.cur_hp > 0 && !effects.0.is_empty() {
+                    turn_damage.insert(*region_id, effects.on_turn_start());
+                }
+            }
+
+            if let Some(en) = region_entity_map.0.get(id) {
+                if let Ok((_, enemy, mut effects)) = enemy_query.get_mut(*en) {
+                    let mut defender = enemy.to_combatant(&effects);
+                    defender.hp -= turn_damage.remove(id).unwrap_or(0);
+                    let outcome = combat::resolve(
+                        &player_status.to_combatant(&player_effects),
+                        &defender,
+                        &skill.event.to_attack(),
+                    );
+                    change_enemy_hp_event
+                        .send(ChangeEnemyHpEvent(*id, outcome.defender_hp - enemy.cur_hp));
+                    player_status.cur_hp = outcome.attacker_hp;
+                    *player_effects = outcome.attacker_effects;
+                    *effects = outcome.defender_effects;
+                    // 自爆只波及已揭开的相邻区域, 避免迷雾中的怪物被悄悄击倒
+                    if outcome.explosion > 0 {
+                        for adjacent in regions.tiles[id].adjacent.iter() {
+                            let status = region_entity_map
+                                .0
+                                .get(adjacent)
+                                .and_then(|en| status_query.get(*en).ok());
+                            if status == Some(&RegionStatus::Found) {
+                                change_enemy_hp_event
+                                    .send(ChangeEnemyHpEvent(*adjacent, -outcome.explosion));
                             }
                         }
-                        play_audio_event.send(PlayAudioEvent(AudioSound::Dao5));
-                        skill.cd_timer = skill.clone().cd;
-                        player_skills.skills.push_back(skill.clone());
-                        triggered = true
-                    } else {
-                        player_skills.skills.push_front(skill.clone());
-                        break;
                     }
-                } else {
-                    skill.cd_timer -= 1;
-                    player_skills.skills.push_back(skill.clone());
                 }
             }
+
+            let mut turn_damage = turn_damage.into_iter().collect::<Vec<_>>();
+            turn_damage.sort();
+            for (region_id, damage) in turn_damage {
+                if damage > 0 {
+                    change_enemy_hp_event.send(ChangeEnemyHpEvent(region_id, -damage));
+                }
+            }
+            play_audio_event.send(PlayAudioEvent(AudioSound::Dao5));
         }
     }
 }
@@ -7,7 +7,7 @@ pub struct Weight<T>(pub T, pub f64);
 
 use serde::{Deserialize, Serialize};
 
-use crate::rng::RAND;
+use crate::rng::RNG;
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Pool<T>(Vec<Weight<T>>);
 
@@ -37,30 +37,39 @@ impl<T> Pool<T> {
         self.0.push(item);
     }
 
-    fn random(&self) -> f64 {
-        let (_, value) = RAND.lock().unwrap().random();
-        value
-    }
-
-    fn fetch(&self) -> &Weight<T> {
-        let mut count = 0f64;
-        for w in self.0.iter() {
-            count += w.1;
-        }
-        let p = self.random() * count;
+    /*
+        按 weight(item, 原权重) 返回的权重抽取, 权重全为 0 时返回最后一项
+    */
+    fn fetch_by(&self, rng: &mut RNG, weight: impl Fn(&T, f64) -> f64) -> &Weight<T> {
+        let weights = self
+            .0
+            .iter()
+            .map(|w| weight(&w.0, w.1).max(0.))
+            .collect::<Vec<_>>();
+        let count = weights.iter().sum::<f64>();
+        let (_, value) = rng.random();
+        let p = value * count;
         let mut grand = 0.;
-        for w in self.0.iter() {
-            if p < w.1 + grand && p >= grand {
+        for (w, weight) in self.0.iter().zip(weights) {
+            if p < weight + grand && p >= grand {
                 return w;
             } else {
-                grand += w.1;
+                grand += weight;
             }
         }
         return &self.0[self.0.len() - 1];
     }
 
-    pub fn fetch_item(&self) -> &T {
-        &self.fetch().0
+    pub fn fetch_item(&self, rng: &mut RNG) -> &T {
+        &self.fetch_by(rng, |_, weight| weight).0
+    }
+
+    pub fn fetch_item_by(&self, rng: &mut RNG, weight: impl Fn(&T, f64) -> f64) -> &T {
+        &self.fetch_by(rng, weight).0
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.0.iter().map(|w| &w.0)
     }
 
     pub fn get_probability_list(&self) -> Vec<f64> {
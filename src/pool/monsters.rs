@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::combat::Trait;
+
 use super::Pool;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -11,9 +13,29 @@ pub struct Monster {
     pub def: i64,
     pub hp: u64,
     pub image_label: String,
+    #[serde(default)]
+    pub traits: Vec<Trait>, // 特性, 不随层数缩放
+    #[serde(default)]
+    pub xp: i64, // 击败后获得的经验
+    #[serde(default)]
+    pub gold: i64, // 击败后获得的金币
 }
 
-pub fn get_monsters_pool() -> Pool<Monster> {
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MonstersConfig {
+    pub monsters: Pool<Monster>,
+    pub bosses: Pool<Monster>,
+}
+
+pub fn get_monsters_config() -> MonstersConfig {
     let config = include_str!("../../assets/pool/monsters.ron");
     ron::from_str(config).unwrap()
 }
+
+pub fn get_monsters_pool() -> Pool<Monster> {
+    get_monsters_config().monsters
+}
+
+pub fn get_bosses_pool() -> Pool<Monster> {
+    get_monsters_config().bosses
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-use super::Pool;
+use crate::components::TileType;
+
+use super::{Pool, Weight};
 struct DirectionPoint(Direction, Direction);
 
 #[derive(Clone, Copy)]
@@ -10,9 +12,9 @@ impl AxisDirection {
     pub fn all() -> Vec<AxisDirection> {
         vec![
             AxisDirection(Axis::X, Direction::Just),
+            AxisDirection(Axis::X, Direction::Burden),
             AxisDirection(Axis::Y, Direction::Just),
             AxisDirection(Axis::Y, Direction::Burden),
-            AxisDirection(Axis::Y, Direction::Burden),
         ]
     }
 }
@@ -31,6 +33,26 @@ impl Point {
         let Point(x, y) = self;
         x.abs() < scope.0 && y.abs() < scope.1
     }
+
+    /*
+        与原点切比雪夫距离为 radius 的一圈点, 按曼哈顿距离由近及远排列, 距离相同时顺序固定
+    */
+    pub fn ring(radius: i64) -> Vec<Point> {
+        if radius == 0 {
+            return vec![Point(0, 0)];
+        }
+        let mut points = vec![];
+        for x in -radius..=radius {
+            points.push(Point(x, -radius));
+            points.push(Point(x, radius));
+        }
+        for y in (1 - radius)..radius {
+            points.push(Point(-radius, y));
+            points.push(Point(radius, y));
+        }
+        points.sort_by_key(|Point(x, y)| (x.abs() + y.abs(), *y, *x));
+        points
+    }
 }
 
 impl std::ops::Add<Point> for Point {
@@ -82,7 +104,7 @@ pub enum Axis {
     Y,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 pub enum PlaneOrientation {
     Up,
     Down,
@@ -120,7 +142,317 @@ impl PlaneOrientation {
     }
 }
 
-pub fn get_plane_orientation_pool() -> Pool<Vec<PlaneOrientation>> {
+// terrains.ron 中形状的写法
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum ShapeDef {
+    // 从原点出发依次移动经过的格子
+    Steps(Vec<PlaneOrientation>),
+    /*
+        字符画, `#` 为格子, `.` 或空格为空, 第一行为 y 的正方向
+        按从上到下, 从左到右的顺序, 第一个 `#` 为原点
+        只去掉首尾的空行, 所有行共同的缩进与每行末尾的空白, 行首的空格仍然是空格子
+    */
+    Grid(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeError {
+    Empty,             // 没有任何格子
+    Disconnected,      // 格子之间不连通
+    InvalidChar(char), // 字符画中出现了 `#` `.` 空格 以外的字符
+}
+
+impl std::fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeError::Empty => write!(f, "shape has no cells"),
+            ShapeError::Disconnected => write!(f, "shape cells are not connected"),
+            ShapeError::InvalidChar(c) => {
+                write!(
+                    f,
+                    "unexpected {:?} in grid, use '#' for cells and '.' for gaps",
+                    c
+                )
+            }
+        }
+    }
+}
+
+impl ShapeDef {
+    /*
+        转换为以原点为起点的格子, 与 PlaneOrientation::to_points 的结果一致
+        空的或不连通的形状返回错误
+    */
+    pub fn to_points(&self) -> Result<Vec<Point>, ShapeError> {
+        let points = match self {
+            ShapeDef::Steps(steps) => PlaneOrientation::to_points(steps),
+            ShapeDef::Grid(grid) => {
+                let mut cells = vec![];
+                let rows = grid.lines().map(str::trim_end).collect::<Vec<_>>();
+                // 只去掉首尾的空行, 中间的空行仍然是一行空格子
+                let first = rows.iter().position(|row| !row.is_empty()).unwrap_or(0);
+                let last = rows
+                    .iter()
+                    .rposition(|row| !row.is_empty())
+                    .map_or(0, |i| i + 1);
+                let rows = rows.get(first..last).unwrap_or_default();
+                let indent = rows
+                    .iter()
+                    .filter(|row| !row.is_empty())
+                    .map(|row| row.chars().take_while(|c| *c == ' ').count())
+                    .min()
+                    .unwrap_or(0);
+                for (y, row) in rows.iter().enumerate() {
+                    for (x, c) in row.chars().skip(indent).enumerate() {
+                        match c {
+                            '#' => cells.push(Point(x as i64, -(y as i64))),
+                            '.' | ' ' => (),
+                            c => return Err(ShapeError::InvalidChar(c)),
+                        }
+                    }
+                }
+                let origin = *cells.first().ok_or(ShapeError::Empty)?;
+                cells
+                    .into_iter()
+                    .map(|Point(x, y)| Point(x - origin.0, y - origin.1))
+                    .collect()
+            }
+        };
+        if !is_connected(&points) {
+            return Err(ShapeError::Disconnected);
+        }
+        Ok(points)
+    }
+}
+
+// 格子之间是否四连通
+fn is_connected(points: &[Point]) -> bool {
+    let first = match points.first() {
+        Some(first) => *first,
+        None => return false,
+    };
+    let mut visited = vec![first];
+    let mut i = 0;
+    while i < visited.len() {
+        for step in PlaneOrientation::all() {
+            let next = visited[i] + PlaneOrientation::to_axis_direction(&step);
+            if points.contains(&next) && !visited.contains(&next) {
+                visited.push(next);
+            }
+        }
+        i += 1;
+    }
+    points.iter().all(|p| visited.contains(p))
+}
+
+// 形状展开为哪些变体, 所有变体平分该项的权重
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub enum Expand {
+    #[default]
+    None, // 只使用原形状
+    Rotate, // 四个方向的旋转
+    Mirror, // 原形状与左右镜像
+    All,    // 旋转与镜像的全部组合
+}
+
+impl Expand {
+    // 展开后互不相同的变体, 第一项为原形状
+    pub fn variants(&self, points: &[Point]) -> Vec<Vec<Point>> {
+        let rotate = |points: &[Point]| {
+            points
+                .iter()
+                .map(|Point(x, y)| Point(-*y, *x))
+                .collect::<Vec<_>>()
+        };
+        let mirror = |points: &[Point]| {
+            points
+                .iter()
+                .map(|Point(x, y)| Point(-*x, *y))
+                .collect::<Vec<_>>()
+        };
+        let mut bases = vec![points.to_vec()];
+        if let Expand::Mirror | Expand::All = self {
+            bases.push(mirror(points));
+        }
+        let mut variants: Vec<Vec<Point>> = vec![];
+        for base in bases {
+            let mut current = base;
+            let turns = match self {
+                Expand::Rotate | Expand::All => 4,
+                _ => 1,
+            };
+            for _ in 0..turns {
+                if !variants.iter().any(|v| same_shape(v, &current)) {
+                    variants.push(current.clone());
+                }
+                current = rotate(&current);
+            }
+        }
+        variants
+    }
+}
+
+// 平移到左下角为原点后排序, 用于比较形状是否相同
+fn normalize(points: &[Point]) -> Vec<Point> {
+    let min_x = points.iter().map(|p| p.0).min().unwrap_or(0);
+    let min_y = points.iter().map(|p| p.1).min().unwrap_or(0);
+    let mut normalized = points
+        .iter()
+        .map(|Point(x, y)| Point(x - min_x, y - min_y))
+        .collect::<Vec<_>>();
+    normalized.sort_by_key(|Point(x, y)| (*x, *y));
+    normalized.dedup();
+    normalized
+}
+
+// 忽略原点位置后两个形状是否相同
+pub fn same_shape(a: &[Point], b: &[Point]) -> bool {
+    normalize(a) == normalize(b)
+}
+
+// terrains.ron 中的一项
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TerrainEntry {
+    pub shape: ShapeDef,
+    pub weight: f64,
+    #[serde(default)]
+    pub expand: Expand,
+    #[serde(default)]
+    pub kind: TileType, // 区域的种类, 默认为房间
+}
+
+// 展开后的形状
+#[derive(Debug, Clone)]
+pub struct TerrainShape {
+    pub points: Vec<Point>,
+    pub kind: TileType,
+}
+
+pub fn get_shapes_pool() -> Pool<TerrainShape> {
     let config = include_str!("../../assets/pool/terrains.ron");
-    ron::from_str(config).unwrap()
+    let entries: Vec<TerrainEntry> = ron::from_str(config).unwrap();
+    Pool::from_items(expand_entries(&entries))
+}
+
+/*
+    将每项展开为格子并合并种类相同的相同形状, 合并后权重相加
+    形状有误时指出是第几项
+*/
+fn expand_entries(entries: &[TerrainEntry]) -> Vec<Weight<TerrainShape>> {
+    let mut shapes: Vec<Weight<TerrainShape>> = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        let points = entry
+            .shape
+            .to_points()
+            .unwrap_or_else(|err| panic!("terrains.ron entry #{}: {}", i, err));
+        let variants = entry.expand.variants(&points);
+        let weight = entry.weight / variants.len() as f64;
+        for variant in variants {
+            match shapes
+                .iter_mut()
+                .find(|w| w.0.kind == entry.kind && same_shape(&w.0.points, &variant))
+            {
+                Some(existing) => existing.1 += weight,
+                None => shapes.push(Weight(
+                    TerrainShape {
+                        points: variant,
+                        kind: entry.kind,
+                    },
+                    weight,
+                )),
+            }
+        }
+    }
+    shapes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(s: &str) -> Result<Vec<Point>, ShapeError> {
+        ShapeDef::Grid(s.to_string()).to_points()
+    }
+
+    #[test]
+    fn leading_space_is_an_empty_cell() {
+        let points = grid(" #\n##").unwrap();
+        assert_eq!(points, vec![Point(0, 0), Point(-1, -1), Point(0, -1)]);
+        assert_eq!(grid(" #\n##"), grid(".#\n##"));
+    }
+
+    #[test]
+    fn common_indentation_is_removed() {
+        assert_eq!(grid("    ##\n     #  "), grid("##\n.#"));
+        assert_eq!(grid("\n  #\n  #\n"), grid("#\n#"));
+    }
+
+    #[test]
+    fn blank_grid_is_empty() {
+        assert_eq!(grid(""), Err(ShapeError::Empty));
+        assert_eq!(grid("\n  \n"), Err(ShapeError::Empty));
+        assert_eq!(grid("..\n.."), Err(ShapeError::Empty));
+    }
+
+    #[test]
+    fn separated_cells_are_disconnected() {
+        assert_eq!(grid("#.#"), Err(ShapeError::Disconnected));
+        assert_eq!(grid("#\n.#"), Err(ShapeError::Disconnected));
+        // 中间的空行是一行空格子, 上下两格不相邻
+        assert_eq!(grid("#\n\n#"), Err(ShapeError::Disconnected));
+        assert_eq!(grid("#\n   \n#"), Err(ShapeError::Disconnected));
+    }
+
+    #[test]
+    fn unknown_chars_are_rejected() {
+        assert_eq!(grid("#x"), Err(ShapeError::InvalidChar('x')));
+        assert_eq!(grid("#\n\t#"), Err(ShapeError::InvalidChar('\t')));
+    }
+
+    #[test]
+    fn rotate_gives_four_variants_of_an_l() {
+        let variants = Expand::Rotate.variants(&grid("#.\n##").unwrap());
+        assert_eq!(variants.len(), 4);
+        for (i, a) in variants.iter().enumerate() {
+            for b in variants.iter().skip(i + 1) {
+                assert!(!same_shape(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn all_removes_symmetric_duplicates() {
+        assert_eq!(Expand::All.variants(&grid("##\n##").unwrap()).len(), 1);
+        assert_eq!(Expand::All.variants(&grid("###").unwrap()).len(), 2);
+        // 三格的 L 镜像后与某个旋转相同, 四格的 L 则不同
+        assert_eq!(Expand::All.variants(&grid("#.\n##").unwrap()).len(), 4);
+        assert_eq!(Expand::All.variants(&grid("#..\n###").unwrap()).len(), 8);
+    }
+
+    #[test]
+    fn merged_duplicates_sum_their_weights() {
+        let entries: Vec<TerrainEntry> = ron::from_str(
+            "[
+                (shape: Steps([Right]), weight: 4, expand: Rotate),
+                (shape: Steps([Up]), weight: 1),
+                (shape: Steps([Up]), weight: 1, kind: Obstacle),
+            ]",
+        )
+        .unwrap();
+        let shapes = expand_entries(&entries);
+        let weight_of = |points: &[Point], kind: TileType| {
+            shapes
+                .iter()
+                .filter(|w| w.0.kind == kind && same_shape(&w.0.points, points))
+                .map(|w| w.1)
+                .collect::<Vec<_>>()
+        };
+        // 横竖两个变体各分得 2, 竖线与第二项合并, 种类不同的第三项不合并
+        let horizontal = [Point(0, 0), Point(1, 0)];
+        let vertical = [Point(0, 0), Point(0, 1)];
+        assert_eq!(shapes.len(), 3);
+        assert_eq!(weight_of(&horizontal, TileType::Room), vec![2.]);
+        assert_eq!(weight_of(&vertical, TileType::Room), vec![3.]);
+        assert_eq!(weight_of(&vertical, TileType::Obstacle), vec![1.]);
+    }
 }
@@ -1,7 +1,8 @@
 use bevy::prelude::Component;
 use serde::{Deserialize, Serialize};
 
-use crate::rng::RAND;
+use crate::effects::Effect;
+use crate::rng::RNG;
 
 use super::Pool;
 
@@ -20,13 +21,14 @@ pub enum KeyValue {
     PlayerAtk(Val),
     PlayerDef(Val),
     PlayerGold(Val),
+    PlayerEffect(Effect), // 为玩家施加状态效果, 不随层数缩放
 }
 
 impl Val {
-    pub fn to_i64(&self) -> i64 {
+    pub fn to_i64(&self, rng: &mut RNG) -> i64 {
         match self {
             Val::Fixed(v) => *v,
-            Val::Float(min, max) => RAND.lock().unwrap().random_range_i64(*min, *max).1,
+            Val::Float(min, max) => rng.random_range_i64(*min, *max).1,
         }
     }
 
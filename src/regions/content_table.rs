@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::{ContentKind, TileType};
+use crate::pool::values::Val;
+use crate::pool::{Pool, Weight};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContentWeight {
+    pub kind: ContentKind,
+    pub weight: f64,
+    #[serde(default)]
+    pub per_cell: f64, // 区域每多一个格子增加的权重
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContentTable {
+    pub trap_damage: Val,
+    pub shop_price: Val,
+    pub tables: HashMap<TileType, Vec<ContentWeight>>,
+}
+
+impl ContentTable {
+    // 指定种类与格子数的区域抽取内容时使用的权重
+    pub fn pool(&self, tile_type: TileType, area: usize) -> Pool<ContentKind> {
+        let weights = match self.tables.get(&tile_type) {
+            Some(weights) if !weights.is_empty() => weights
+                .iter()
+                .map(|w| Weight(w.kind, w.weight + w.per_cell * area as f64))
+                .collect(),
+            _ => vec![Weight(ContentKind::Empty, 1.)],
+        };
+        Pool::from_items(weights)
+    }
+}
+
+pub fn get_content_table() -> ContentTable {
+    let config = include_str!("../../assets/config/contents.ron");
+    ron::from_str(config).unwrap()
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// 由 (x, y) 组成的折线, x 需从小到大排列
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Curve(pub Vec<(f64, f64)>);
+
+impl Curve {
+    // 线性插值, 超出范围时取端点的值, 没有点时为 1
+    pub fn sample(&self, x: f64) -> f64 {
+        let points = &self.0;
+        match (points.first(), points.last()) {
+            (Some(first), _) if x <= first.0 => first.1,
+            (_, Some(last)) if x >= last.0 => last.1,
+            (None, _) | (_, None) => 1.,
+            _ => {
+                let i = points.iter().position(|(px, _)| *px > x).unwrap();
+                let (x0, y0) = points[i - 1];
+                let (x1, y1) = points[i];
+                y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DifficultyConfig {
+    pub monster_weights: HashMap<String, Curve>,
+    pub value_weights: HashMap<String, Curve>,
+    pub monster_stats: Curve,
+}
+
+impl DifficultyConfig {
+    pub fn monster_weight(&self, name: &str, depth: f64) -> f64 {
+        self.monster_weights
+            .get(name)
+            .map_or(1., |curve| curve.sample(depth))
+    }
+
+    pub fn value_weight(&self, name: &str, depth: f64) -> f64 {
+        self.value_weights
+            .get(name)
+            .map_or(1., |curve| curve.sample(depth))
+    }
+
+    pub fn monster_stats(&self, depth: f64) -> f64 {
+        self.monster_stats.sample(depth)
+    }
+}
+
+pub fn get_difficulty_config() -> DifficultyConfig {
+    let config = include_str!("../../assets/config/difficulty.ron");
+    ron::from_str(config).unwrap()
+}
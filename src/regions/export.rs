@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::components::TileType;
+use crate::pool::terrains::{PlaneOrientation, Point};
+
+use super::manager::{Regions, Tile};
+
+fn type_char(tile_type: TileType) -> char {
+    match tile_type {
+        TileType::Started => 'S',
+        TileType::Room => 'R',
+        TileType::Obstacle => 'O',
+        TileType::Corridor => 'C',
+        TileType::Shrine => 'H',
+        TileType::Vault => 'V',
+    }
+}
+
+fn type_color(tile_type: TileType) -> &'static str {
+    match tile_type {
+        TileType::Started => "#3cb371",
+        TileType::Room => "#d2b48c",
+        TileType::Obstacle => "#696969",
+        TileType::Corridor => "#a0a0a0",
+        TileType::Shrine => "#87ceeb",
+        TileType::Vault => "#ffd700",
+    }
+}
+
+#[derive(Serialize)]
+struct TileDump {
+    id: u64,
+    tile_type: TileType,
+    shape: Vec<(i64, i64)>,
+    adjacent: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct MapDump {
+    scope: (i64, i64),
+    start: Option<u64>,
+    tiles: Vec<TileDump>,
+}
+
+impl Regions {
+    // 按id排序的方块列表, 保证导出结果稳定
+    fn sorted_tiles(&self) -> Vec<&Tile> {
+        let mut tiles = self.tiles.values().collect::<Vec<_>>();
+        tiles.sort_by_key(|tile| tile.id);
+        tiles
+    }
+
+    // 所有格子的包围盒 (min_x, min_y, max_x, max_y)
+    fn bounds(&self) -> Option<(i64, i64, i64, i64)> {
+        let mut points = self.tile_map.keys();
+        let Point(x, y) = *points.next()?;
+        Some(points.fold((x, y, x, y), |(x0, y0, x1, y1), Point(x, y)| {
+            (x0.min(*x), y0.min(*y), x1.max(*x), y1.max(*y))
+        }))
+    }
+
+    /*
+        每个格子输出为 类型+id, 空格子输出为 `.`, 上方为 y 的正方向
+        类型: S 起始, R 房间, O 障碍, C 走廊, H 神龛, V 宝库
+    */
+    pub fn to_ascii(&self) -> String {
+        let (x0, y0, x1, y1) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+        let width = format!("{}", self.inc_id).len() + 1;
+        let mut lines = vec![];
+        for y in (y0..=y1).rev() {
+            let line = (x0..=x1)
+                .map(|x| match self.tile_map.get(&Point(x, y)) {
+                    Some(id) => {
+                        let tile_type = self.tiles[id].tile_type;
+                        format!("{}{:<w$}", type_char(tile_type), id, w = width - 1)
+                    }
+                    None => format!("{:<w$}", ".", w = width),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(line.trim_end().to_string());
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn to_json(&self) -> String {
+        let dump = MapDump {
+            scope: self.scope,
+            start: self.start,
+            tiles: self
+                .sorted_tiles()
+                .into_iter()
+                .map(|tile| {
+                    let mut shape = tile.shape.iter().map(|p| (p.0, p.1)).collect::<Vec<_>>();
+                    shape.sort();
+                    let mut adjacent = tile.adjacent.iter().copied().collect::<Vec<_>>();
+                    adjacent.sort();
+                    TileDump {
+                        id: tile.id,
+                        tile_type: tile.tile_type,
+                        shape,
+                        adjacent,
+                    }
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&dump).unwrap()
+    }
+
+    /*
+        按格子绘制每个方块, 不同方块之间以及方块外侧的边缘绘制边框, 同一方块内部的格子之间不画线
+    */
+    pub fn to_svg(&self, size: f32) -> String {
+        let (x0, y0, x1, y1) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::from("<svg xmlns=\"http://www.w3.org/2000/svg\"/>\n"),
+        };
+        let width = (x1 - x0 + 1) as f32 * size;
+        let height = (y1 - y0 + 1) as f32 * size;
+        // svg 的 y 轴向下, 需要翻转
+        let to_svg = |Point(x, y): Point| ((x - x0) as f32 * size, (y1 - y) as f32 * size);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            width, height, width, height
+        );
+        svg += "<rect width=\"100%\" height=\"100%\" fill=\"#1e1e1e\"/>\n";
+        for tile in self.sorted_tiles() {
+            let color = type_color(tile.tile_type);
+            let mut cells = tile.shape.iter().copied().collect::<Vec<_>>();
+            cells.sort_by_key(|Point(x, y)| (*x, *y));
+            svg += &format!("<g id=\"tile-{}\">\n", tile.id);
+            for cell in cells.iter() {
+                let (x, y) = to_svg(*cell);
+                svg += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x, y, size, size, color
+                );
+            }
+            for cell in cells.iter() {
+                let (x, y) = to_svg(*cell);
+                for step in PlaneOrientation::all() {
+                    let neighbour = *cell + PlaneOrientation::to_axis_direction(&step);
+                    if tile.shape.contains(&neighbour) {
+                        continue;
+                    }
+                    let (ax, ay, bx, by) = match step {
+                        PlaneOrientation::Up => (x, y, x + size, y),
+                        PlaneOrientation::Down => (x, y + size, x + size, y + size),
+                        PlaneOrientation::Left => (x, y, x, y + size),
+                        PlaneOrientation::Right => (x + size, y, x + size, y + size),
+                    };
+                    svg += &format!(
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#1e1e1e\" stroke-width=\"2\"/>\n",
+                        ax, ay, bx, by
+                    );
+                }
+            }
+            if let Some(cell) = cells.first() {
+                let (x, y) = to_svg(*cell);
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                    x + size / 2.,
+                    y + size / 2.,
+                    size / 2.,
+                    tile.id
+                );
+            }
+            svg += "</g>\n";
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::GameStage;
+
+use super::region_entity_map::CurrentOverRegion;
+
+pub struct RegionFlagPlugin;
+
+impl Plugin for RegionFlagPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RegionFlags>()
+            .add_system_set(SystemSet::on_update(GameStage::Main).with_system(flag_with_keyboard));
+    }
+}
+
+// 玩家对区域的标记
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionFlag {
+    Danger,   // 危险, 标记后点击不会进入该区域
+    Treasure, // 可能有宝物
+    Later,    // 稍后再来
+}
+
+impl RegionFlag {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RegionFlag::Danger => "危",
+            RegionFlag::Treasure => "宝",
+            RegionFlag::Later => "待",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            RegionFlag::Danger => Color::RED,
+            RegionFlag::Treasure => Color::GOLD,
+            RegionFlag::Later => Color::AQUAMARINE,
+        }
+    }
+
+    // 右键依次切换 无 -> 危险 -> 宝物 -> 稍后 -> 无
+    pub fn cycle(flag: Option<RegionFlag>) -> Option<RegionFlag> {
+        match flag {
+            None => Some(RegionFlag::Danger),
+            Some(RegionFlag::Danger) => Some(RegionFlag::Treasure),
+            Some(RegionFlag::Treasure) => Some(RegionFlag::Later),
+            Some(RegionFlag::Later) => None,
+        }
+    }
+}
+
+// 以区域id为键的标记, 每层开始时清空, 游戏还没有存档, 标记不会保存
+#[derive(Resource, Debug, Default, Clone)]
+pub struct RegionFlags(pub HashMap<u64, RegionFlag>);
+
+impl RegionFlags {
+    pub fn get(&self, id: u64) -> Option<RegionFlag> {
+        self.0.get(&id).copied()
+    }
+
+    pub fn set(&mut self, id: u64, flag: Option<RegionFlag>) {
+        match flag {
+            Some(flag) => self.0.insert(id, flag),
+            None => self.0.remove(&id),
+        };
+    }
+
+    // 已有相同标记时取消, 否则替换为该标记
+    pub fn toggle(&mut self, id: u64, flag: RegionFlag) {
+        if self.get(id) == Some(flag) {
+            self.set(id, None);
+        } else {
+            self.set(id, Some(flag));
+        }
+    }
+
+    pub fn is_danger(&self, id: u64) -> bool {
+        self.get(id) == Some(RegionFlag::Danger)
+    }
+}
+
+// 1 / 2 / 3 键标记鼠标所在的区域
+fn flag_with_keyboard(
+    keys: Res<Input<KeyCode>>,
+    current_over_region: Res<CurrentOverRegion>,
+    mut flags: ResMut<RegionFlags>,
+) {
+    let id = match *current_over_region {
+        CurrentOverRegion::Region(id) => id,
+        CurrentOverRegion::None => return,
+    };
+    let flag = if keys.just_pressed(KeyCode::Key1) {
+        RegionFlag::Danger
+    } else if keys.just_pressed(KeyCode::Key2) {
+        RegionFlag::Treasure
+    } else if keys.just_pressed(KeyCode::Key3) {
+        RegionFlag::Later
+    } else {
+        return;
+    };
+    flags.toggle(id, flag);
+}
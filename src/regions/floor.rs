@@ -0,0 +1,79 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::pool::values::{KeyValue, Val, Value};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FloorConfig {
+    pub count: u32,
+    pub monster_growth: f64,
+    pub value_growth: f64,
+}
+
+impl FloorConfig {
+    // 第 floor 层的怪物属性倍率, 第一层为 1
+    pub fn monster_scale(&self, floor: u32) -> f64 {
+        1. + self.monster_growth * floor.saturating_sub(1) as f64
+    }
+
+    // 第 floor 层的物品数值倍率, 第一层为 1
+    pub fn value_scale(&self, floor: u32) -> f64 {
+        1. + self.value_growth * floor.saturating_sub(1) as f64
+    }
+
+    pub fn is_last(&self, floor: u32) -> bool {
+        floor >= self.count
+    }
+}
+
+pub fn get_floor_config() -> FloorConfig {
+    let config = include_str!("../../assets/config/floors.ron");
+    ron::from_str(config).unwrap()
+}
+
+// 当前所在的层数, 从 1 开始
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentFloor(pub u32);
+
+impl Default for CurrentFloor {
+    fn default() -> Self {
+        CurrentFloor(1)
+    }
+}
+
+// 使用楼梯前往下一层
+pub struct DescendEvent;
+
+pub fn scale_i64(value: i64, scale: f64) -> i64 {
+    (value as f64 * scale).round() as i64
+}
+
+impl Val {
+    pub fn scaled(&self, scale: f64) -> Val {
+        match self {
+            Val::Fixed(v) => Val::Fixed(scale_i64(*v, scale)),
+            Val::Float(min, max) => Val::Float(scale_i64(*min, scale), scale_i64(*max, scale)),
+        }
+    }
+}
+
+impl Value {
+    pub fn scaled(&self, scale: f64) -> Value {
+        let values = self
+            .values
+            .iter()
+            .map(|value| match value {
+                KeyValue::PlayerCurrentHp(val) => KeyValue::PlayerCurrentHp(val.scaled(scale)),
+                KeyValue::PlayerMaxHp(val) => KeyValue::PlayerMaxHp(val.scaled(scale)),
+                KeyValue::PlayerAtk(val) => KeyValue::PlayerAtk(val.scaled(scale)),
+                KeyValue::PlayerDef(val) => KeyValue::PlayerDef(val.scaled(scale)),
+                KeyValue::PlayerGold(val) => KeyValue::PlayerGold(val.scaled(scale)),
+                KeyValue::PlayerEffect(effect) => KeyValue::PlayerEffect(*effect),
+            })
+            .collect();
+        Value {
+            values,
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::components::TileType;
+use crate::pool::terrains::Point;
+use crate::rng::RNG;
+
+use super::super::manager::Regions;
+use super::MapGenerator;
+
+pub struct BspGenerator {
+    pub min_size: i64,        // 房间的最小边长
+    pub corridor_chance: f64, // 房间切出走廊的概率
+}
+
+// 以左下角与宽高表示的矩形区域
+#[derive(Clone, Copy)]
+struct Area {
+    x: i64,
+    y: i64,
+    w: i64,
+    h: i64,
+}
+
+impl Area {
+    fn contains(&self, Point(x, y): Point) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+
+    fn points(&self) -> Vec<Point> {
+        (self.x..self.x + self.w)
+            .flat_map(|x| (self.y..self.y + self.h).map(move |y| Point(x, y)))
+            .collect()
+    }
+}
+
+impl BspGenerator {
+    fn split(&self, area: Area, rng: &mut RNG, leaves: &mut Vec<Area>) {
+        let min_size = self.min_size.max(2);
+        let can_split_x = area.w >= min_size * 2;
+        let can_split_y = area.h >= min_size * 2;
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => {
+                leaves.push(area);
+                return;
+            }
+            (true, false) => true,
+            (false, true) => false,
+            // 优先切割较长的一边, 相差不大时随机
+            (true, true) => {
+                if area.w * 4 > area.h * 5 {
+                    true
+                } else if area.h * 4 > area.w * 5 {
+                    false
+                } else {
+                    rng.random_boolean().1
+                }
+            }
+        };
+        if split_x {
+            let (_, cut) = rng.random_range_i64(min_size, area.w - min_size);
+            self.split(Area { w: cut, ..area }, rng, leaves);
+            self.split(
+                Area {
+                    x: area.x + cut,
+                    w: area.w - cut,
+                    ..area
+                },
+                rng,
+                leaves,
+            );
+        } else {
+            let (_, cut) = rng.random_range_i64(min_size, area.h - min_size);
+            self.split(Area { h: cut, ..area }, rng, leaves);
+            self.split(
+                Area {
+                    y: area.y + cut,
+                    h: area.h - cut,
+                    ..area
+                },
+                rng,
+                leaves,
+            );
+        }
+    }
+}
+
+impl MapGenerator for BspGenerator {
+    fn generate(&self, regions: &mut Regions, rng: &mut RNG) {
+        let start = Point(0, 0);
+        let _ = regions.place_start(start);
+        let (scope_x, scope_y) = regions.scope;
+        let mut leaves = vec![];
+        self.split(
+            Area {
+                x: 1 - scope_x,
+                y: 1 - scope_y,
+                w: scope_x * 2 - 1,
+                h: scope_y * 2 - 1,
+            },
+            rng,
+            &mut leaves,
+        );
+        for leaf in leaves {
+            // 起始方块所在的房间不切走廊, 避免房间被起始方块分成两半
+            if !leaf.contains(start) && leaf.w.max(leaf.h) >= 3 {
+                let (_, is_corridor) = rng.random_val_boolean(self.corridor_chance);
+                if is_corridor {
+                    let (room, corridor) = if leaf.w >= leaf.h {
+                        (
+                            Area {
+                                w: leaf.w - 1,
+                                ..leaf
+                            },
+                            Area {
+                                x: leaf.x + leaf.w - 1,
+                                w: 1,
+                                ..leaf
+                            },
+                        )
+                    } else {
+                        (
+                            Area {
+                                h: leaf.h - 1,
+                                ..leaf
+                            },
+                            Area {
+                                y: leaf.y + leaf.h - 1,
+                                h: 1,
+                                ..leaf
+                            },
+                        )
+                    };
+                    let _ = regions.place_shape(room.points(), TileType::Room);
+                    let _ = regions.place_shape(corridor.points(), TileType::Corridor);
+                    continue;
+                }
+            }
+            let _ = regions.place_shape(
+                leaf.points().into_iter().filter(|p| *p != start),
+                TileType::Room,
+            );
+        }
+    }
+}
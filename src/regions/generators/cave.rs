@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::components::TileType;
+use crate::pool::terrains::{PlaneOrientation, Point};
+use crate::rng::RNG;
+
+use super::super::manager::Regions;
+use super::MapGenerator;
+
+pub struct CaveGenerator {
+    pub fill: f64,  // 初始时每个格子为墙的概率
+    pub steps: u32, // 元胞自动机的迭代次数
+    pub chunk: i64, // 切分方块时的网格大小
+}
+
+// 4 连通的分量, 分量内的点按坐标排序
+fn components(cells: &HashSet<Point>) -> Vec<Vec<Point>> {
+    let mut sorted = cells.iter().copied().collect::<Vec<_>>();
+    sorted.sort_by_key(|Point(x, y)| (*x, *y));
+    let mut visited = HashSet::new();
+    let mut result = vec![];
+    for cell in sorted {
+        if !visited.insert(cell) {
+            continue;
+        }
+        let mut component = vec![];
+        let mut queue = VecDeque::from([cell]);
+        while let Some(p) = queue.pop_front() {
+            component.push(p);
+            for step in PlaneOrientation::all() {
+                let next = p + PlaneOrientation::to_axis_direction(&step);
+                if cells.contains(&next) && visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        component.sort_by_key(|Point(x, y)| (*x, *y));
+        result.push(component);
+    }
+    result
+}
+
+impl CaveGenerator {
+    // 随机填充后反复平滑, 返回所有地面格子
+    fn floors(&self, scope: (i64, i64), rng: &mut RNG) -> HashSet<Point> {
+        let (scope_x, scope_y) = scope;
+        let mut walls = HashMap::new();
+        for x in (1 - scope_x)..scope_x {
+            for y in (1 - scope_y)..scope_y {
+                walls.insert(Point(x, y), rng.random_val_boolean(self.fill).1);
+            }
+        }
+        for _ in 0..self.steps {
+            walls = walls
+                .iter()
+                .map(|(p, wall)| {
+                    // 超出地图的格子视为墙
+                    let count = (-1..=1)
+                        .flat_map(|dx| (-1..=1).map(move |dy| Point(dx, dy)))
+                        .filter(|d| *d != Point(0, 0))
+                        .filter(|d| *walls.get(&(*p + *d)).unwrap_or(&true))
+                        .count();
+                    (*p, count >= 5 || (*wall && count >= 4))
+                })
+                .collect();
+        }
+        let mut floors = walls
+            .into_iter()
+            .filter(|(_, wall)| !wall)
+            .map(|(p, _)| p)
+            .collect::<HashSet<_>>();
+
+        // 起始位置周围至少保留一块空地, 并挖一条通道连到最大的洞穴
+        for x in -1..=1 {
+            for y in -1..=1 {
+                floors.insert(Point(x, y));
+            }
+        }
+        let caves = components(&floors);
+        if let Some(largest) = caves.iter().max_by_key(|cave| cave.len()) {
+            if !largest.contains(&Point(0, 0)) {
+                let Point(tx, ty) = *largest
+                    .iter()
+                    .min_by_key(|Point(x, y)| (x.abs() + y.abs(), *x, *y))
+                    .unwrap();
+                for x in tx.min(0)..=tx.max(0) {
+                    floors.insert(Point(x, 0));
+                }
+                for y in ty.min(0)..=ty.max(0) {
+                    floors.insert(Point(tx, y));
+                }
+            }
+        }
+        floors
+    }
+}
+
+impl MapGenerator for CaveGenerator {
+    fn generate(&self, regions: &mut Regions, rng: &mut RNG) {
+        let start = Point(0, 0);
+        let _ = regions.place_start(start);
+        let mut floors = self.floors(regions.scope, rng);
+        floors.remove(&start);
+
+        // 按网格切分洞穴, 每个网格中的每个连通分量成为一个方块
+        let chunk = self.chunk.max(2);
+        let mut chunks: HashMap<(i64, i64), HashSet<Point>> = HashMap::new();
+        for p in floors {
+            let Point(x, y) = p;
+            chunks
+                .entry((x.div_euclid(chunk), y.div_euclid(chunk)))
+                .or_default()
+                .insert(p);
+        }
+        let mut keys = chunks.keys().copied().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            for component in components(&chunks[&key]) {
+                // 单格的分量留作墙壁, 只有一行或一列宽的分量作为走廊
+                if component.len() > 1 {
+                    let first = component[0];
+                    let tile_type = if component.iter().all(|p| p.0 == first.0)
+                        || component.iter().all(|p| p.1 == first.1)
+                    {
+                        TileType::Corridor
+                    } else {
+                        TileType::Room
+                    };
+                    let _ = regions.place_shape(component, tile_type);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+mod bsp;
+mod cave;
+mod prefab;
+mod scatter;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pool::terrains::get_shapes_pool;
+use crate::rng::RNG;
+
+use super::manager::Regions;
+
+pub use self::bsp::BspGenerator;
+pub use self::cave::CaveGenerator;
+pub use self::prefab::PrefabGenerator;
+pub use self::scatter::ScatterGenerator;
+
+/*
+    地图生成器, 在已清空的 regions 上生成方块并设置起始方块
+    不连通方块的删除由 Regions::generate 统一处理
+*/
+pub trait MapGenerator {
+    fn generate(&self, regions: &mut Regions, rng: &mut RNG);
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum GeneratorKind {
+    // 在地图上随机撒下 terrains.ron 中的形状
+    Scatter { attempts: u64 },
+    // 二分切割地图, 每个叶子成为一个房间, 部分房间切出一条走廊
+    Bsp { min_size: i64, corridor_chance: f64 },
+    // 元胞自动机生成洞穴, 再按 chunk 大小切分为方块
+    Cave { fill: f64, steps: u32, chunk: i64 },
+    // 以起始方块为中心, 不断将 terrains.ron 中的形状拼接到已有方块旁
+    Prefab { attempts: u64 },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GeneratorConfig {
+    pub scope: (i64, i64),
+    pub generator: GeneratorKind,
+}
+
+impl GeneratorConfig {
+    pub fn build(&self) -> Box<dyn MapGenerator> {
+        match self.generator {
+            GeneratorKind::Scatter { attempts } => Box::new(ScatterGenerator {
+                attempts,
+                shapes: get_shapes_pool(),
+            }),
+            GeneratorKind::Bsp {
+                min_size,
+                corridor_chance,
+            } => Box::new(BspGenerator {
+                min_size,
+                corridor_chance,
+            }),
+            GeneratorKind::Cave { fill, steps, chunk } => {
+                Box::new(CaveGenerator { fill, steps, chunk })
+            }
+            GeneratorKind::Prefab { attempts } => Box::new(PrefabGenerator {
+                attempts,
+                shapes: get_shapes_pool(),
+            }),
+        }
+    }
+}
+
+pub fn get_generator_config() -> GeneratorConfig {
+    let config = include_str!("../../../assets/config/generator.ron");
+    ron::from_str(config).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 与 generator.ron 注释中列出的配置相同
+    fn configured_generators() -> Vec<(&'static str, Box<dyn MapGenerator>)> {
+        [
+            "Scatter(attempts: 1024)",
+            "Bsp(min_size: 3, corridor_chance: 0.3)",
+            "Cave(fill: 0.45, steps: 4, chunk: 3)",
+            "Prefab(attempts: 256)",
+        ]
+        .into_iter()
+        .map(|generator| {
+            let config = format!("(scope: (32, 32), generator: {})", generator);
+            let config = ron::from_str::<GeneratorConfig>(&config).unwrap();
+            (generator, config.build())
+        })
+        .collect()
+    }
+
+    fn generate(generator: &dyn MapGenerator, seed: u64) -> Regions {
+        let mut regions = Regions {
+            scope: (32, 32),
+            ..Default::default()
+        };
+        regions.generate(generator, &mut RNG::new(seed));
+        regions
+    }
+
+    #[test]
+    fn same_seed_gives_same_map() {
+        for (name, generator) in configured_generators() {
+            for seed in 0..2 {
+                let a = generate(&*generator, seed).to_json();
+                let b = generate(&*generator, seed).to_json();
+                assert_eq!(a, b, "{} seed {}", name, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn start_exists_and_map_is_connected() {
+        for (name, generator) in configured_generators() {
+            for seed in 0..4 {
+                let regions = generate(&*generator, seed);
+                let start = regions.start.expect(name);
+                assert!(regions.tiles.contains_key(&start), "{} seed {}", name, seed);
+                assert!(regions.tiles.len() > 1, "{} seed {}", name, seed);
+                let components = regions.connected_components();
+                assert_eq!(components.len(), 1, "{} seed {}", name, seed);
+                assert_eq!(
+                    regions.distance_map(start).len(),
+                    regions.tiles.len(),
+                    "{} seed {}",
+                    name,
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_generator_has_a_stairs_position() {
+        for (name, generator) in configured_generators() {
+            for seed in 0..4 {
+                let regions = generate(&*generator, seed);
+                let exit = regions.farthest_exit();
+                assert!(exit.is_some(), "{} seed {}", name, seed);
+                assert_ne!(exit, regions.start);
+            }
+        }
+    }
+}
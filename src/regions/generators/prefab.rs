@@ -0,0 +1,63 @@
+use crate::pool::terrains::{PlaneOrientation, Point, TerrainShape};
+use crate::pool::Pool;
+use crate::rng::RNG;
+
+use super::super::manager::Regions;
+use super::MapGenerator;
+
+pub struct PrefabGenerator {
+    pub attempts: u64,
+    pub shapes: Pool<TerrainShape>,
+}
+
+// 从列表中随机取出一个元素
+fn pick<'a, T>(items: &'a [T], rng: &mut RNG) -> &'a T {
+    let (_, index) = rng.random_range_i64(0, items.len() as i64 - 1);
+    &items[index as usize]
+}
+
+impl MapGenerator for PrefabGenerator {
+    fn generate(&self, regions: &mut Regions, rng: &mut RNG) {
+        let _ = regions.place_start(Point(0, 0));
+        let directions = PlaneOrientation::all();
+        for _ in 0..self.attempts {
+            let TerrainShape {
+                points: shape,
+                kind,
+            } = self.shapes.fetch_item(rng);
+
+            // 随机选择一个紧贴已有方块的空格子作为门
+            let mut doors = regions
+                .tile_map
+                .keys()
+                .flat_map(|p| {
+                    directions
+                        .iter()
+                        .map(move |d| *p + PlaneOrientation::to_axis_direction(d))
+                })
+                .filter(|p| p.in_scope(regions.scope) && !regions.tile_map.contains_key(p))
+                .collect::<Vec<_>>();
+            doors.sort_by_key(|Point(x, y)| (*x, *y));
+            doors.dedup();
+            if doors.is_empty() {
+                break;
+            }
+            let door = *pick(&doors, rng);
+
+            // 让形状中的某一格落在门上, 保证新方块与已有方块相邻, 依次尝试每一格
+            let (_, first) = rng.random_range_i64(0, shape.len() as i64 - 1);
+            for i in 0..shape.len() {
+                let anchor = shape[(first as usize + i) % shape.len()];
+                let offset = Point(door.0 - anchor.0, door.1 - anchor.1);
+                let moved = shape.iter().map(|p| *p + offset).collect::<Vec<_>>();
+                if moved
+                    .iter()
+                    .all(|p| p.in_scope(regions.scope) && !regions.tile_map.contains_key(p))
+                {
+                    let _ = regions.place_shape(moved, *kind);
+                    break;
+                }
+            }
+        }
+    }
+}
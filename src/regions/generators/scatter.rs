@@ -0,0 +1,30 @@
+use crate::pool::terrains::{Point, TerrainShape};
+use crate::pool::Pool;
+use crate::rng::RNG;
+
+use super::super::manager::Regions;
+use super::MapGenerator;
+
+pub struct ScatterGenerator {
+    pub attempts: u64,
+    pub shapes: Pool<TerrainShape>,
+}
+
+impl MapGenerator for ScatterGenerator {
+    fn generate(&self, regions: &mut Regions, rng: &mut RNG) {
+        // 固定在中心生成一个1x1的方块
+        let _ = regions.place_start(Point(0, 0));
+        for _ in 0..self.attempts {
+            let x = ((rng.random().1 - 0.5) * regions.scope.0 as f64).floor() as i64;
+            let y = ((rng.random().1 - 0.5) * regions.scope.1 as f64).floor() as i64;
+            let shape = self.shapes.fetch_item(rng);
+            // 放不下的tile直接丢弃
+            let _ = regions.make_and_adjust_tile(
+                Point(x, y),
+                shape.points.as_slice(),
+                shape.kind,
+                None,
+            );
+        }
+    }
+}
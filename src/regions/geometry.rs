@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use bevy::math::{Rect, Vec2};
+use bevy::utils::HashSet;
+
+use crate::pool::terrains::Point;
+
+/*
+    将方块的格子转换为一组矩形, 坐标以格子 Point(0, 0) 的中心为原点
+    每个格子向内收缩 gap / 2, 同一方块内相邻的格子之间用桥接矩形填满间隙,
+    只有方块与方块之间留出 gap 宽的缝隙
+*/
+pub fn cell_rects(shape: &HashSet<Point>, size: f32, gap: f32) -> Vec<Rect> {
+    let half = size / 2.;
+    let inner = half - gap / 2.;
+    let outer = half + gap / 2.;
+    let mut cells = shape.iter().copied().collect::<Vec<_>>();
+    cells.sort_by_key(|Point(x, y)| (*x, *y));
+
+    let mut rects = vec![];
+    for Point(x, y) in cells {
+        let center = Vec2::new(x as f32, y as f32) * size;
+        let right = shape.contains(&Point(x + 1, y));
+        let up = shape.contains(&Point(x, y + 1));
+        let up_right = shape.contains(&Point(x + 1, y + 1));
+
+        rects.push(Rect::from_corners(
+            center - Vec2::splat(inner),
+            center + Vec2::splat(inner),
+        ));
+        if right {
+            rects.push(Rect::from_corners(
+                center + Vec2::new(inner, -inner),
+                center + Vec2::new(outer, inner),
+            ));
+        }
+        if up {
+            rects.push(Rect::from_corners(
+                center + Vec2::new(-inner, inner),
+                center + Vec2::new(inner, outer),
+            ));
+        }
+        // 四个格子都属于同一方块时, 中间的十字缝隙交点也需要填满
+        if right && up && up_right {
+            rects.push(Rect::from_corners(
+                center + Vec2::splat(inner),
+                center + Vec2::splat(outer),
+            ));
+        }
+    }
+    rects
+}
+
+/*
+    将世界坐标换算为格子并在 tile_map 中查找所属方块
+    落在方块之间 gap 缝隙中的坐标不属于任何方块, 与 cell_rects 绘制的形状一致
+*/
+pub fn tile_at(tile_map: &HashMap<Point, u64>, pos: Vec2, size: f32, gap: f32) -> Option<u64> {
+    let x = (pos.x / size).round() as i64;
+    let y = (pos.y / size).round() as i64;
+    let id = tile_map.get(&Point(x, y))?;
+    let same = |dx: i64, dy: i64| tile_map.get(&Point(x + dx, y + dy)) == Some(id);
+
+    let local = pos - Vec2::new(x as f32, y as f32) * size;
+    let inner = size / 2. - gap / 2.;
+    // 靠近哪一侧的边缘, 0 表示不在缝隙中
+    let side = |v: f32| {
+        if v > inner {
+            1
+        } else if v < -inner {
+            -1
+        } else {
+            0
+        }
+    };
+    let (sx, sy) = (side(local.x), side(local.y));
+    if (sx != 0 && !same(sx, 0)) || (sy != 0 && !same(0, sy)) {
+        return None;
+    }
+    if sx != 0 && sy != 0 && !same(sx, sy) {
+        return None;
+    }
+    Some(*id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 32.;
+    const GAP: f32 = 4.;
+
+    fn shape(points: &[(i64, i64)]) -> HashSet<Point> {
+        points.iter().map(|(x, y)| Point(*x, *y)).collect()
+    }
+
+    fn covered(rects: &[Rect], pos: Vec2) -> bool {
+        rects.iter().any(|rect| rect.contains(pos))
+    }
+
+    #[test]
+    fn single_cell_is_inset_by_half_gap() {
+        let rects = cell_rects(&shape(&[(0, 0)]), SIZE, GAP);
+        assert_eq!(
+            rects,
+            vec![Rect::from_corners(Vec2::splat(-14.), Vec2::splat(14.))]
+        );
+    }
+
+    #[test]
+    fn adjacent_cells_bridge_the_gap() {
+        let rects = cell_rects(&shape(&[(0, 0), (1, 0)]), SIZE, GAP);
+        assert_eq!(rects.len(), 3);
+        // 两个格子之间的缝隙被填满, 外侧仍然留出半个 gap
+        assert!(covered(&rects, Vec2::new(16., 0.)));
+        assert!(covered(&rects, Vec2::new(46., 0.)));
+        assert!(!covered(&rects, Vec2::new(47., 0.)));
+        assert!(!covered(&rects, Vec2::new(16., 15.)));
+    }
+
+    #[test]
+    fn l_shape_leaves_the_inner_corner_open() {
+        let rects = cell_rects(&shape(&[(0, 0), (1, 0), (0, 1)]), SIZE, GAP);
+        assert_eq!(rects.len(), 5);
+        assert!(covered(&rects, Vec2::new(16., 0.)));
+        assert!(covered(&rects, Vec2::new(0., 16.)));
+        // 缺少右上角的格子, 十字缝隙的交点与右上格子都不填
+        assert!(!covered(&rects, Vec2::new(16., 16.)));
+        assert!(!covered(&rects, Vec2::new(32., 32.)));
+    }
+
+    // 方块 1 占 (0, 0) 与 (1, 0), 方块 2 占 (0, 1), 方块 3 占 (-1, -1)
+    fn tile_map() -> HashMap<Point, u64> {
+        HashMap::from([
+            (Point(0, 0), 1),
+            (Point(1, 0), 1),
+            (Point(0, 1), 2),
+            (Point(-1, -1), 3),
+        ])
+    }
+
+    #[test]
+    fn cell_centre_hits_its_tile() {
+        let map = tile_map();
+        assert_eq!(tile_at(&map, Vec2::new(0., 0.), SIZE, GAP), Some(1));
+        assert_eq!(tile_at(&map, Vec2::new(32., 0.), SIZE, GAP), Some(1));
+        assert_eq!(tile_at(&map, Vec2::new(0., 32.), SIZE, GAP), Some(2));
+    }
+
+    #[test]
+    fn gap_between_tiles_hits_nothing() {
+        let map = tile_map();
+        assert_eq!(tile_at(&map, Vec2::new(0., 15.), SIZE, GAP), None);
+        assert_eq!(tile_at(&map, Vec2::new(0., 17.), SIZE, GAP), None);
+        // 空格子旁边的缝隙
+        assert_eq!(tile_at(&map, Vec2::new(-15., 0.), SIZE, GAP), None);
+    }
+
+    #[test]
+    fn gap_inside_a_tile_hits_the_tile() {
+        let map = tile_map();
+        assert_eq!(tile_at(&map, Vec2::new(15., 0.), SIZE, GAP), Some(1));
+        assert_eq!(tile_at(&map, Vec2::new(17., 0.), SIZE, GAP), Some(1));
+    }
+
+    #[test]
+    fn negative_coordinates() {
+        let map = tile_map();
+        assert_eq!(tile_at(&map, Vec2::new(-32., -32.), SIZE, GAP), Some(3));
+        assert_eq!(tile_at(&map, Vec2::new(-40., -25.), SIZE, GAP), Some(3));
+        assert_eq!(tile_at(&map, Vec2::new(-32., -64.), SIZE, GAP), None);
+        assert_eq!(tile_at(&map, Vec2::new(-17., -17.), SIZE, GAP), None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::marks::{EnemyStatus, HintText, RegionId, RegionStatus};
+use crate::GameStage;
+
+use super::manager::Tile;
+use super::pure::RegionMark;
+use super::Regions;
+
+pub struct RegionHintPlugin;
+
+impl Plugin for RegionHintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HintMode>().add_system_set(
+            SystemSet::on_update(GameStage::Main)
+                .with_system(toggle_hint_mode)
+                .with_system(update_hint_text_system),
+        );
+    }
+}
+
+/*
+    已发现区域上危险提示的显示方式, 按 H 键切换
+    访问区域时相邻的迷雾区域都会变为 Found, 已访问区域旁没有迷雾, 因此提示显示在 Found 区域上
+*/
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HintMode {
+    #[default]
+    Off,
+    Count,    // 相邻仍在迷雾中的区域里的怪物数量
+    Strength, // 相邻仍在迷雾中的区域里怪物的攻击力之和
+}
+
+impl HintMode {
+    pub fn next(&self) -> HintMode {
+        match self {
+            HintMode::Off => HintMode::Count,
+            HintMode::Count => HintMode::Strength,
+            HintMode::Strength => HintMode::Off,
+        }
+    }
+
+    // 单个怪物计入提示数字的权重
+    pub fn weight(&self, enemy: &EnemyStatus) -> i64 {
+        match self {
+            HintMode::Off => 0,
+            HintMode::Count => 1,
+            HintMode::Strength => enemy.atk,
+        }
+    }
+}
+
+/*
+    相邻区域的危险程度之和, dangers 为仍在迷雾中且有存活怪物的区域及其权重
+    已经揭开的怪物玩家能直接看到, 不计入提示
+*/
+pub fn danger_hint(tile: &Tile, dangers: &HashMap<u64, i64>) -> i64 {
+    tile.adjacent.iter().filter_map(|id| dangers.get(id)).sum()
+}
+
+fn toggle_hint_mode(keys: Res<Input<KeyCode>>, mut hint_mode: ResMut<HintMode>) {
+    if keys.just_pressed(KeyCode::H) {
+        *hint_mode = hint_mode.next();
+    }
+}
+
+fn update_hint_text_system(
+    asset_server: Res<AssetServer>,
+    hint_mode: Res<HintMode>,
+    regions: Res<Regions>,
+    region_query: Query<(&RegionId, &RegionStatus, Option<&EnemyStatus>), With<RegionMark>>,
+    changed_query: Query<(), (With<RegionMark>, Changed<RegionStatus>)>,
+    added_query: Query<(), Added<HintText>>,
+    mut text_query: Query<(&HintText, &mut Text, &mut Visibility)>,
+) {
+    // 只在区域状态变化, 提示文字新建或切换显示方式时重新计算
+    if !hint_mode.is_changed() && changed_query.is_empty() && added_query.is_empty() {
+        return;
+    }
+    let mut statuses = HashMap::new();
+    let mut dangers = HashMap::new();
+    for (RegionId(id), status, enemy) in region_query.iter() {
+        statuses.insert(*id, *status);
+        if let Some(enemy) = enemy {
+            if *status == RegionStatus::Mist && enemy.cur_hp > 0 {
+                dangers.insert(*id, hint_mode.weight(enemy));
+            }
+        }
+    }
+
+    for (HintText(id), mut text, mut visibility) in text_query.iter_mut() {
+        let hint = match (regions.tiles.get(id), statuses.get(id)) {
+            (Some(tile), Some(RegionStatus::Found)) if *hint_mode != HintMode::Off => {
+                danger_hint(tile, &dangers)
+            }
+            _ => 0,
+        };
+        if visibility.is_visible != (hint > 0) {
+            visibility.is_visible = hint > 0;
+        }
+        let label = hint.to_string();
+        if hint > 0 && text.sections.first().map(|s| &s.value) != Some(&label) {
+            *text = Text::from_section(
+                label,
+                TextStyle {
+                    font: asset_server.load("fonts/hanti.ttf"),
+                    font_size: 14.0,
+                    color: Color::ORANGE_RED,
+                },
+            )
+            .with_alignment(TextAlignment::CENTER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::TileType;
+    use crate::pool::terrains::Point;
+
+    // 与 change_region_status_system 相同: 访问的区域变为 Visited, 相邻的迷雾区域变为 Found
+    fn visit(regions: &Regions, statuses: &mut HashMap<u64, RegionStatus>, id: u64) {
+        statuses.insert(id, RegionStatus::Visited);
+        for adjacent in regions.tiles[&id].adjacent.iter() {
+            if statuses[adjacent] == RegionStatus::Mist {
+                statuses.insert(*adjacent, RegionStatus::Found);
+            }
+        }
+    }
+
+    #[test]
+    fn found_regions_count_enemies_still_in_mist() {
+        // 一行 5 个方块: 起点 a b c d, 怪物在 c 与 d
+        let mut regions = Regions::default();
+        let start = regions.place_start(Point(0, 0)).unwrap();
+        let ids = (1..5)
+            .map(|x| regions.place_shape([Point(x, 0)], TileType::Room).unwrap())
+            .collect::<Vec<_>>();
+        let mut statuses = regions
+            .tiles
+            .keys()
+            .map(|id| (*id, RegionStatus::Mist))
+            .collect::<HashMap<_, _>>();
+        visit(&regions, &mut statuses, start);
+        visit(&regions, &mut statuses, ids[0]);
+        assert_eq!(statuses[&ids[1]], RegionStatus::Found);
+        assert_eq!(statuses[&ids[2]], RegionStatus::Mist);
+
+        let dangers = [ids[2], ids[3]]
+            .iter()
+            .filter(|id| statuses[*id] == RegionStatus::Mist)
+            .map(|id| (*id, 1))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(danger_hint(&regions.tiles[&ids[1]], &dangers), 1);
+        // 已访问区域的邻居都已揭开, 不会有提示
+        assert_eq!(danger_hint(&regions.tiles[&ids[0]], &dangers), 0);
+        assert_eq!(danger_hint(&regions.tiles[&start], &dangers), 0);
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::components::TileType;
+use crate::pool::monsters::get_monsters_config;
+use crate::pool::terrains::{Point, ShapeDef, ShapeError};
+use crate::pool::values::get_values_pool;
+use crate::rng::RNG;
+
+use super::generators::MapGenerator;
+use super::manager::Regions;
+
+// 方块中固定的内容, 名称对应 monsters.ron 与 values.ron 中的 name
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Content {
+    Monster(String),
+    Boss(String),
+    Value(String),
+    Empty,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TileDef {
+    pub pos: (i64, i64),
+    pub shape: ShapeDef, // 与 terrains.ron 的写法相同, 原点放在 pos
+    #[serde(default)]
+    pub kind: TileType, // 区域的种类, 默认为房间
+    #[serde(default)]
+    pub content: Option<Content>, // 未指定时与随机地图一样抽取内容
+}
+
+impl TileDef {
+    fn points(&self) -> Result<Vec<Point>, ShapeError> {
+        let points = self.shape.to_points()?;
+        Ok(points
+            .into_iter()
+            .map(|p| p + Point(self.pos.0, self.pos.1))
+            .collect())
+    }
+}
+
+// 手工编写的关卡, 只有一层
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Level {
+    pub name: String,
+    pub scope: (i64, i64),
+    pub start: (i64, i64),
+    pub tiles: Vec<TileDef>,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Parse(ron::error::SpannedError),
+    Start,                    // 起始方块超出范围
+    Shape(usize, ShapeError), // 第 n 个方块的形状有误
+    Placement(usize),         // 第 n 个方块与其他方块重叠或超出范围
+    Unreachable(usize),       // 第 n 个方块与起始方块不连通
+    UnknownContent(String),   // 怪物或物品的名称不存在
+}
+
+impl std::fmt::Display for LevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelError::Parse(err) => write!(f, "invalid level file: {}", err),
+            LevelError::Start => write!(f, "start tile is out of scope"),
+            LevelError::Shape(i, err) => write!(f, "tile #{}: {}", i, err),
+            LevelError::Placement(i) => write!(f, "tile #{} overlaps or is out of scope", i),
+            LevelError::Unreachable(i) => write!(f, "tile #{} is not connected to the start", i),
+            LevelError::UnknownContent(name) => write!(f, "unknown monster or value {}", name),
+        }
+    }
+}
+
+impl Level {
+    /*
+        解析关卡并检查所有方块都能放在指定位置且与起始方块连通
+        固定内容的名称需要能在 monsters.ron 与 values.ron 中找到
+    */
+    pub fn load(source: &str) -> Result<Level, LevelError> {
+        let level: Level = ron::from_str(source).map_err(LevelError::Parse)?;
+        level.check_contents()?;
+        let mut regions = Regions {
+            scope: level.scope,
+            ..Default::default()
+        };
+        let start = Point(level.start.0, level.start.1);
+        let start = regions.place_start(start).map_err(|_| LevelError::Start)?;
+        for (i, tile) in level.tiles.iter().enumerate() {
+            let points = tile.points().map_err(|err| LevelError::Shape(i, err))?;
+            regions
+                .place_shape(points, tile.kind)
+                .map_err(|_| LevelError::Placement(i))?;
+        }
+        let reachable = regions.distance_map(start);
+        for (i, tile) in level.tiles.iter().enumerate() {
+            let id = regions.tile_map[&Point(tile.pos.0, tile.pos.1)];
+            if !reachable.contains_key(&id) {
+                return Err(LevelError::Unreachable(i));
+            }
+        }
+        Ok(level)
+    }
+
+    fn check_contents(&self) -> Result<(), LevelError> {
+        let monsters = get_monsters_config();
+        let values = get_values_pool();
+        for tile in self.tiles.iter() {
+            let (name, known) = match &tile.content {
+                Some(Content::Monster(name)) | Some(Content::Boss(name)) => (
+                    name,
+                    monsters
+                        .monsters
+                        .items()
+                        .chain(monsters.bosses.items())
+                        .any(|monster| &monster.name == name),
+                ),
+                Some(Content::Value(name)) => {
+                    (name, values.items().any(|value| &value.name == name))
+                }
+                Some(Content::Empty) | None => continue,
+            };
+            if !known {
+                return Err(LevelError::UnknownContent(name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // 生成后各方块id对应的固定内容
+    pub fn contents(&self, regions: &Regions) -> HashMap<u64, Content> {
+        self.tiles
+            .iter()
+            .filter_map(|tile| {
+                let id = regions.tile_map.get(&Point(tile.pos.0, tile.pos.1))?;
+                Some((*id, tile.content.clone()?))
+            })
+            .collect()
+    }
+}
+
+// 关卡已在 load 中检查过, 按顺序放置即可, 不消耗随机数
+impl MapGenerator for Level {
+    fn generate(&self, regions: &mut Regions, _rng: &mut RNG) {
+        regions.scope = self.scope;
+        regions
+            .place_start(Point(self.start.0, self.start.1))
+            .expect("level start tile must fit");
+        for tile in self.tiles.iter() {
+            let points = tile.points().expect("level shapes must be valid");
+            regions
+                .place_shape(points, tile.kind)
+                .expect("level tiles must not overlap");
+        }
+    }
+}
+
+pub fn get_tutorial_level() -> Level {
+    let config = include_str!("../../assets/levels/tutorial.ron");
+    Level::load(config).unwrap()
+}
+
+/*
+    启动参数 `--level <path>` 指定的关卡, `--level tutorial` 为内置的教程关卡
+    未指定时使用随机生成的多层地牢
+*/
+#[derive(Resource, Debug, Default)]
+pub struct SelectedLevel(pub Option<Level>);
+
+impl SelectedLevel {
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let level = args
+            .iter()
+            .position(|arg| arg == "--level")
+            .and_then(|i| args.get(i + 1))
+            .map(|path| match path.as_str() {
+                "tutorial" => get_tutorial_level(),
+                path => {
+                    let source = std::fs::read_to_string(path)
+                        .unwrap_or_else(|err| panic!("failed to read level {}: {}", path, err));
+                    Level::load(&source).unwrap_or_else(|err| panic!("{}: {}", path, err))
+                }
+            });
+        SelectedLevel(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_with(content: &str) -> String {
+        format!(
+            "(name: \"x\", scope: (4, 4), start: (0, 0), tiles: [(pos: (1, 0), shape: Steps([]), content: Some({}))])",
+            content
+        )
+    }
+
+    #[test]
+    fn known_contents_load() {
+        assert!(Level::load(&level_with("Monster(\"狌狌\")")).is_ok());
+        assert!(Level::load(&level_with("Boss(\"蛊雕\")")).is_ok());
+        assert!(Level::load(&level_with("Value(\"泉\")")).is_ok());
+    }
+
+    #[test]
+    fn unknown_contents_are_rejected() {
+        for content in ["Monster(\"猩猩\")", "Boss(\"蛊\")", "Value(\"水\")"] {
+            assert!(matches!(
+                Level::load(&level_with(content)),
+                Err(LevelError::UnknownContent(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn tutorial_level_is_valid() {
+        let level = Level::load(include_str!("../../assets/levels/tutorial.ron"))
+            .unwrap_or_else(|err| panic!("tutorial.ron: {}", err));
+        let mut regions = Regions::default();
+        let connectivity = regions.generate(&level, &mut RNG::new(0));
+        // 所有方块加上起始方块都放在地图上且与起始方块连通
+        assert_eq!(regions.tiles.len(), level.tiles.len() + 1);
+        assert!(connectivity.pruned.is_empty());
+        let fixed = level.tiles.iter().filter(|tile| tile.content.is_some());
+        assert_eq!(level.contents(&regions).len(), fixed.count());
+    }
+}
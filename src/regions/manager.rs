@@ -1,20 +1,22 @@
 use std::borrow::BorrowMut;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
 use std::iter::FromIterator;
 
 use crate::components::TileType;
-use crate::pool::Pool;
+use crate::rng::RNG;
 use bevy::prelude::Transform;
 use bevy::utils::HashSet;
 use bevy::{math::Vec3, prelude::Resource};
 
 use super::super::pool::terrains::{AxisDirection, PlaneOrientation, Point};
+use super::generators::MapGenerator;
 
 #[derive(Debug, Clone)]
 pub struct Tile {
     pub id: u64,
     pub adjacent: HashSet<u64>,
     pub shape: HashSet<Point>,
+    pub tile_type: TileType,
 }
 
 impl Tile {
@@ -49,27 +51,51 @@ impl Tile {
         }
         None
     }
-    pub fn to_tile_type(&self) -> TileType {
-        if self.shape.len() == 1 {
-            return TileType::Started;
-        }
-        if let Some(Point(first_point_x, first_point_y)) = self.shape.iter().next() {
-            if self.shape.iter().all(|Point(x, ..)| x == first_point_x)
-                || self.shape.iter().all(|Point(.., y)| y == first_point_y)
-            {
-                return TileType::Obstacle;
-            }
+}
+
+// tile 无法放置的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    AlreadyPlaced, // tile 已经在地图上
+    NoRoom,        // 搜索范围内没有能放下 tile 的空位
+}
+
+// 本次生成中放置失败而被丢弃的 tile 数, 按原因统计
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PlacementFailures {
+    pub already_placed: usize,
+    pub no_room: usize,
+}
+
+impl PlacementFailures {
+    pub fn record(&mut self, error: PlacementError) {
+        match error {
+            PlacementError::AlreadyPlaced => self.already_placed += 1,
+            PlacementError::NoRoom => self.no_room += 1,
         }
-        TileType::Room
+    }
+
+    pub fn total(&self) -> usize {
+        self.already_placed + self.no_room
     }
 }
 
+// 地图的连通性统计
+#[derive(Debug, Default, Clone)]
+pub struct Connectivity {
+    pub reachable: usize,       // 与起始方块连通的方块数(含起始方块)
+    pub pruned: Vec<u64>,       // 因不连通而被删除的方块
+    pub components: Vec<usize>, // 删除前各连通分量的大小, 从大到小
+}
+
 #[derive(Resource, Debug)]
 pub struct Regions {
     pub inc_id: u64,
     pub scope: (i64, i64),
+    pub start: Option<u64>,            // 起始方块的id
     pub tile_map: HashMap<Point, u64>, // x,y,id
     pub tiles: HashMap<u64, Tile>,
+    pub failures: PlacementFailures, // clear 时清零
 }
 
 impl Default for Regions {
@@ -77,8 +103,10 @@ impl Default for Regions {
         Self {
             inc_id: 0,
             scope: (32, 32),
+            start: None,
             tile_map: Default::default(),
             tiles: Default::default(),
+            failures: Default::default(),
         }
     }
 }
@@ -86,91 +114,367 @@ impl Default for Regions {
 impl Regions {
     pub fn clear(&mut self) {
         self.inc_id = 0;
+        self.start = None;
         self.tile_map.clear();
         self.tiles.clear();
+        self.failures = PlacementFailures::default();
     }
 
-    pub fn make_tile(&mut self, pos: Point, steps: &[PlaneOrientation]) -> Tile {
-        let shape = HashSet::from_iter(
-            PlaneOrientation::to_points(steps)
-                .into_iter()
-                .map(|p| p + pos),
-        );
+    /*
+        从指定方块出发沿 adjacent 做广度优先遍历, 返回每个可达方块到它的步数
+    */
+    pub fn distance_map(&self, from: u64) -> HashMap<u64, usize> {
+        let mut distances = HashMap::new();
+        if !self.tiles.contains_key(&from) {
+            return distances;
+        }
+        let mut queue = VecDeque::new();
+        distances.insert(from, 0);
+        queue.push_back(from);
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[&id];
+            let mut adjacent = self.tiles[&id].adjacent.iter().copied().collect::<Vec<_>>();
+            adjacent.sort();
+            for next in adjacent {
+                if let Entry::Vacant(entry) = distances.entry(next) {
+                    entry.insert(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /*
+        从 from 出发只经过 passable 的方块做广度优先遍历, 按遍历顺序返回经过的方块(不含 from)
+        相邻方块按id排序, 同样的输入总是得到同样的顺序
+    */
+    pub fn flood(&self, from: &[u64], passable: impl Fn(u64) -> bool) -> Vec<u64> {
+        let mut seen = from.iter().copied().collect::<HashSet<_>>();
+        let mut queue = from.iter().copied().collect::<VecDeque<_>>();
+        let mut result = vec![];
+        while let Some(id) = queue.pop_front() {
+            let mut adjacent = match self.tiles.get(&id) {
+                Some(tile) => tile.adjacent.iter().copied().collect::<Vec<_>>(),
+                None => continue,
+            };
+            adjacent.sort();
+            for next in adjacent {
+                if !seen.contains(&next) && passable(next) {
+                    seen.insert(next);
+                    result.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        result
+    }
+
+    // 所有连通分量, 按大小从大到小排列, 大小相同时按最小id排列
+    pub fn connected_components(&self) -> Vec<Vec<u64>> {
+        let mut ids = self.tiles.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        let mut visited = HashSet::default();
+        let mut components = vec![];
+        for id in ids {
+            if visited.contains(&id) {
+                continue;
+            }
+            let mut component = self.distance_map(id).into_keys().collect::<Vec<_>>();
+            component.sort();
+            visited.extend(component.iter().copied());
+            components.push(component);
+        }
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        components
+    }
+
+    pub fn connectivity(&self) -> Connectivity {
+        let reachable = match self.start {
+            Some(start) => self.distance_map(start).len(),
+            None => 0,
+        };
+        Connectivity {
+            reachable,
+            pruned: vec![],
+            components: self
+                .connected_components()
+                .iter()
+                .map(|component| component.len())
+                .collect(),
+        }
+    }
+
+    // 距离起始方块步数最多且满足 filter 的方块, 步数相同时取id最小的
+    fn farthest(&self, filter: impl Fn(&Tile) -> bool) -> Option<u64> {
+        let start = self.start?;
+        self.distance_map(start)
+            .into_iter()
+            .filter(|(id, _)| filter(&self.tiles[id]))
+            .max_by(|(a, a_distance), (b, b_distance)| a_distance.cmp(b_distance).then(b.cmp(a)))
+            .map(|(id, _)| id)
+    }
+
+    pub fn farthest_room(&self) -> Option<u64> {
+        self.farthest(|tile| tile.tile_type == TileType::Room)
+    }
+
+    /*
+        放置楼梯或首领的方块, 优先取最远的房间
+        没有房间时(如洞穴地图)取最远的非起始方块, 只有起始方块时返回 None
+    */
+    pub fn farthest_exit(&self) -> Option<u64> {
+        self.farthest_room()
+            .or_else(|| self.farthest(|tile| tile.tile_type != TileType::Started))
+    }
+
+    /*
+        删除所有与起始方块不连通的方块, 同时维护 tile_map 与 tiles 的一致性
+    */
+    pub fn prune_unreachable(&mut self) -> Connectivity {
+        let mut connectivity = self.connectivity();
+        let reachable = match self.start {
+            Some(start) => self.distance_map(start),
+            None => HashMap::new(),
+        };
+        let mut pruned = self
+            .tiles
+            .keys()
+            .filter(|id| !reachable.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        pruned.sort();
+        for id in pruned.iter() {
+            if let Some(tile) = self.tiles.remove(id) {
+                for point in tile.shape.iter() {
+                    self.tile_map.remove(point);
+                }
+                for adjacent_id in tile.adjacent.iter() {
+                    if let Some(adjacent) = self.tiles.get_mut(adjacent_id) {
+                        adjacent.adjacent.remove(id);
+                    }
+                }
+            }
+        }
+        connectivity.pruned = pruned;
+        connectivity
+    }
+
+    // shape 为以原点为起点的格子, 平移到 pos
+    pub fn make_tile(&mut self, pos: Point, shape: &[Point], tile_type: TileType) -> Tile {
+        self.make_tile_from_shape(shape.iter().map(|p| *p + pos), tile_type)
+    }
+
+    pub fn make_tile_from_shape(
+        &mut self,
+        shape: impl IntoIterator<Item = Point>,
+        tile_type: TileType,
+    ) -> Tile {
         self.inc_id += 1;
         Tile {
             id: self.inc_id,
             adjacent: HashSet::default(),
-            shape,
+            shape: HashSet::from_iter(shape),
+            tile_type,
         }
     }
 
+    // 最大搜索半径, 超出该半径仍放不下的tile视为放置失败
+    pub fn placement_radius(&self) -> i64 {
+        self.scope.0.max(self.scope.1) / 2
+    }
+
+    // tile 平移 offset 后是否能放在地图上
+    pub fn fits(&self, tile: &Tile, offset: Point) -> bool {
+        tile.shape.iter().all(|p| {
+            let target = *p + offset;
+            target.in_scope(self.scope) && !self.tile_map.contains_key(&target)
+        })
+    }
+
     /*
-        按指定方向将tile调整到一个合适的位置并计算其连通性, 若未提供方向则每次都随机调整方向
+        按指定方向将tile调整到一个合适的位置并计算其连通性, 若未提供方向则由近及远逐圈搜索最近的空位
+        搜索范围由 placement_radius 限定, 找不到位置时返回 PlacementError::NoRoom
     */
-    pub fn adjust_tile(&mut self, tile: &mut Tile, axis_direction: Option<AxisDirection>) -> bool {
+    pub fn adjust_tile(
+        &mut self,
+        tile: &mut Tile,
+        axis_direction: Option<AxisDirection>,
+    ) -> Result<(), PlacementError> {
         // 若已在tile_map中存在则不会再次进行调整
-        if self.tile_map.values().any(|k| *k == tile.id) {
-            return false;
+        if self.tiles.contains_key(&tile.id) {
+            self.failures.record(PlacementError::AlreadyPlaced);
+            return Err(PlacementError::AlreadyPlaced);
         }
-        while tile.shape.iter().any(|p| self.tile_map.get(p) != None) {
-            if let Some(axis_direction_value) = axis_direction {
-                tile.shape =
-                    HashSet::from_iter(tile.shape.iter().map(|p| *p + axis_direction_value));
-            } else {
-                let axis_direction_value_index =
-                    (rand::random::<f32>() * AxisDirection::all().len() as f32).floor() as usize;
-                let axis_direction_value = AxisDirection::all()[axis_direction_value_index];
-                tile.shape =
-                    HashSet::from_iter(tile.shape.iter().map(|p| *p + axis_direction_value));
+        let radius = self.placement_radius();
+        let offset = match axis_direction {
+            Some(axis_direction_value) => {
+                std::iter::successors(Some(Point(0, 0)), |p| Some(*p + axis_direction_value))
+                    .take(radius as usize + 1)
+                    .find(|offset| self.fits(tile, *offset))
             }
-        }
-        // 判断tile是否还在scope的限制当中
-        if tile.shape.iter().all(|p| p.in_scope(self.scope)) {
-            tile.shape.iter().for_each(|p| {
-                // 标记边缘连通性
-                for step in PlaneOrientation::all() {
-                    let target_point = *p + PlaneOrientation::to_axis_direction(&step);
-                    if let Some(id) = self.tile_map.get(&target_point) {
-                        if *id != tile.id {
-                            // 当前tile 添加联通标记
-                            tile.adjacent.insert(*id);
-                            // 被联通tile 添加联通标记
-                            self.tiles.get_mut(id).unwrap().adjacent.insert(tile.id);
-                        }
+            None => (0..=radius)
+                .flat_map(Point::ring)
+                .find(|offset| self.fits(tile, *offset)),
+        };
+        let Some(offset) = offset else {
+            self.failures.record(PlacementError::NoRoom);
+            return Err(PlacementError::NoRoom);
+        };
+        tile.shape = HashSet::from_iter(tile.shape.iter().map(|p| *p + offset));
+        self.place_tile(tile);
+        Ok(())
+    }
+
+    /*
+        将已确定位置的tile写入地图并计算其连通性, 调用方需保证tile不与已有方块重叠
+    */
+    pub fn place_tile(&mut self, tile: &mut Tile) {
+        tile.shape.iter().for_each(|p| {
+            // 标记边缘连通性
+            for step in PlaneOrientation::all() {
+                let target_point = *p + PlaneOrientation::to_axis_direction(&step);
+                if let Some(id) = self.tile_map.get(&target_point) {
+                    if *id != tile.id {
+                        // 当前tile 添加联通标记
+                        tile.adjacent.insert(*id);
+                        // 被联通tile 添加联通标记
+                        self.tiles.get_mut(id).unwrap().adjacent.insert(tile.id);
                     }
                 }
-                self.tile_map.insert(*p, tile.id);
-            });
-            // 将tile 加入tiles
-            self.tiles.insert(tile.id, tile.clone());
-            return true;
+            }
+            self.tile_map.insert(*p, tile.id);
+        });
+        // 将tile 加入tiles
+        self.tiles.insert(tile.id, tile.clone());
+    }
+
+    /*
+        将给定形状原样放到地图上, 与已有方块重叠或超出scope时返回 PlacementError::NoRoom
+    */
+    pub fn place_shape(
+        &mut self,
+        shape: impl IntoIterator<Item = Point>,
+        tile_type: TileType,
+    ) -> Result<u64, PlacementError> {
+        let mut tile = self.make_tile_from_shape(shape, tile_type);
+        if !self.fits(&tile, Point(0, 0)) {
+            self.failures.record(PlacementError::NoRoom);
+            return Err(PlacementError::NoRoom);
         }
-        false
+        self.place_tile(&mut tile);
+        Ok(tile.id)
     }
 
-    fn make_and_adjust_tile(
+    // 在指定位置放置1x1的起始方块
+    pub fn place_start(&mut self, pos: Point) -> Result<u64, PlacementError> {
+        let id = self.place_shape([pos], TileType::Started)?;
+        self.start = Some(id);
+        Ok(id)
+    }
+
+    /*
+        清空地图后使用指定的生成器生成方块, 并删除与起始方块不连通的方块
+    */
+    pub fn generate(&mut self, generator: &dyn MapGenerator, rng: &mut RNG) -> Connectivity {
+        self.clear();
+        generator.generate(self, rng);
+        self.prune_unreachable()
+    }
+
+    pub fn make_and_adjust_tile(
         &mut self,
         pos: Point,
-        steps: &[PlaneOrientation],
+        shape: &[Point],
+        tile_type: TileType,
         axis_direction: Option<AxisDirection>,
-    ) -> Option<Tile> {
-        let mut tile = self.make_tile(pos, steps);
-        match self.adjust_tile(tile.borrow_mut(), axis_direction) {
-            true => Some(tile),
-            false => None,
-        }
+    ) -> Result<Tile, PlacementError> {
+        let mut tile = self.make_tile(pos, shape, tile_type);
+        self.adjust_tile(tile.borrow_mut(), axis_direction)?;
+        Ok(tile)
     }
-    pub fn random_generate_tiles(
-        &mut self,
-        times: u64,
-        move_steps_pool: &Pool<Vec<PlaneOrientation>>,
-    ) {
-        self.make_and_adjust_tile(Point(0, 0), &[], None); // 固定在中心生成一个1x1的方块
-        for _ in 0..times {
-            let x = ((rand::random::<f32>() - 0.5) as f64 * self.scope.0 as f64).floor() as i64;
-            let y = ((rand::random::<f32>() - 0.5) as f64 * self.scope.1 as f64).floor() as i64;
-            self.make_and_adjust_tile(Point(x, y), move_steps_pool.fetch_item().as_slice(), None);
-        }
-        // TODO 删除所有起始位置不连通的方块
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(regions: &mut Regions, axis_direction: Option<AxisDirection>) -> bool {
+        regions
+            .make_and_adjust_tile(Point(0, 0), &[Point(0, 0)], TileType::Room, axis_direction)
+            .is_ok()
+    }
+
+    #[test]
+    fn placement_stops_when_scope_is_full() {
+        // scope (2, 2) 只有 -1..=1 的 9 个格子, 搜索半径为 1 圈
+        let mut regions = Regions {
+            scope: (2, 2),
+            ..Default::default()
+        };
+        let placed = (0..20).filter(|_| cell(&mut regions, None)).count();
+        assert_eq!(placed, 9);
+        assert_eq!(regions.tile_map.len(), 9);
+        assert_eq!(
+            regions.failures,
+            PlacementFailures {
+                already_placed: 0,
+                no_room: 11,
+            }
+        );
+    }
+
+    #[test]
+    fn directed_placement_stops_at_the_edge() {
+        let mut regions = Regions {
+            scope: (2, 2),
+            ..Default::default()
+        };
+        let right = PlaneOrientation::to_axis_direction(&PlaneOrientation::Right);
+        // 沿 x 正方向只有 (0, 0) 与 (1, 0) 两个位置
+        let placed = (0..5).filter(|_| cell(&mut regions, Some(right))).count();
+        assert_eq!(placed, 2);
+        assert_eq!(regions.failures.no_room, 3);
+    }
+
+    #[test]
+    fn placed_tile_is_rejected_and_recorded() {
+        let mut regions = Regions::default();
+        let mut tile = regions
+            .make_and_adjust_tile(Point(0, 0), &[Point(0, 0)], TileType::Room, None)
+            .unwrap();
+        assert_eq!(
+            regions.adjust_tile(&mut tile, None),
+            Err(PlacementError::AlreadyPlaced)
+        );
+        assert_eq!(regions.failures.already_placed, 1);
+        assert_eq!(regions.failures.total(), 1);
+    }
+
+    #[test]
+    fn prune_unreachable_removes_other_components() {
+        let mut regions = Regions::default();
+        let start = regions.place_start(Point(0, 0)).unwrap();
+        let room = regions
+            .place_shape([Point(1, 0), Point(1, 1)], TileType::Room)
+            .unwrap();
+        // 与起始方块不连通的两个方块, 彼此相邻
+        let a = regions.place_shape([Point(5, 5)], TileType::Room).unwrap();
+        let b = regions.place_shape([Point(5, 6)], TileType::Room).unwrap();
+
+        let connectivity = regions.prune_unreachable();
+        assert_eq!(connectivity.reachable, 2);
+        assert_eq!(connectivity.pruned, vec![a, b]);
+        assert_eq!(connectivity.components, vec![2, 2]);
+
+        let mut ids = regions.tiles.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec![start, room]);
+        assert_eq!(regions.tile_map.len(), 3);
+        assert!(regions
+            .tile_map
+            .values()
+            .all(|id| regions.tiles.contains_key(id)));
+        assert_eq!(regions.connected_components().len(), 1);
     }
 }
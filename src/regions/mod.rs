@@ -1,23 +1,42 @@
+mod content_table;
+mod difficulty;
 pub mod events;
+mod export;
+mod flags;
+mod floor;
+pub mod generators;
+pub mod geometry;
+mod hints;
+pub mod level;
 mod manager;
 mod pure;
 mod region_entity_map;
 mod renderer;
+mod validate;
 
 pub use self::events::{ChangeEnemyHpEvent, ChangeRegionStatusEvent, RegionClickEvent};
-pub use self::manager::Regions;
+pub use self::flags::{RegionFlag, RegionFlags};
+pub use self::floor::{get_floor_config, CurrentFloor, DescendEvent, FloorConfig};
+pub use self::hints::HintMode;
+pub use self::level::{Level, SelectedLevel};
+pub use self::manager::{Connectivity, Regions};
+use self::flags::RegionFlagPlugin;
+use self::hints::RegionHintPlugin;
 use self::pure::RegionPurePlugin;
 pub use self::region_entity_map::{CurrentOverRegion, RegionEntityMap};
 use self::renderer::RegionRenderPlugin;
 use bevy::prelude::Plugin;
 pub use pure::RegionMark;
 pub use renderer::WorldMouse;
+pub use validate::{MapStats, Violation};
 
 pub struct RegionPlugin;
 
 impl Plugin for RegionPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_plugin(RegionPurePlugin)
-            .add_plugin(RegionRenderPlugin);
+            .add_plugin(RegionRenderPlugin)
+            .add_plugin(RegionHintPlugin)
+            .add_plugin(RegionFlagPlugin);
     }
 }
@@ -1,28 +1,35 @@
 use crate::{
-    components::TileType,
+    components::{ContentKind, TileType},
+    effects::StatusEffects,
+    experience::{ExperienceConfig, PendingLevelUps},
     marks::EnemyLabel,
     panel::{HasBattlePanel, HasValuePanel},
     player::PlayerStatus,
     pool::{
-        monsters::{get_monsters_pool, Monster},
-        values::{get_values_pool, Value},
+        monsters::{get_bosses_pool, get_monsters_pool, Monster},
+        values::{get_values_pool, KeyValue, Value},
     },
-    rng::RAND,
+    rng::{self, RngStreams, RunSeed, RAND},
     GameStage,
 };
 use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
 pub struct RegionPurePlugin;
 
-use crate::{
-    marks::{EnemyMark, EnemyStatus, RegionId, RegionStatus},
-    pool::terrains::get_plane_orientation_pool,
+use crate::marks::{
+    BossMark, EnemyMark, EnemyStatus, RegionId, RegionStatus, Shop, StairsMark, Trap,
 };
 
 use super::{
+    content_table::get_content_table,
+    difficulty::get_difficulty_config,
     events::{
         AtkMonsterWithPlayerSkill, AudioSound, MouseOverEmpty, MouseOverRegionEvent, PlayAudioEvent,
     },
-    manager::Tile,
+    flags::RegionFlags,
+    floor::{get_floor_config, scale_i64, CurrentFloor, DescendEvent},
+    generators::get_generator_config,
+    level::{Content, Level, SelectedLevel},
     region_entity_map::{CurrentOverRegion, RegionEntityMap},
     renderer::WorldMouse,
     ChangeEnemyHpEvent, ChangeRegionStatusEvent, RegionClickEvent, Regions,
@@ -31,6 +38,16 @@ use super::{
 #[derive(Component)]
 pub struct RegionMark;
 
+// 访问空区域时是否自动访问相连的空区域, 按 R 键切换
+#[derive(Resource)]
+pub struct AutoReveal(pub bool);
+
+impl Default for AutoReveal {
+    fn default() -> Self {
+        AutoReveal(true)
+    }
+}
+
 impl Plugin for RegionPurePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<RegionClickEvent>()
@@ -40,11 +57,14 @@ impl Plugin for RegionPurePlugin {
             .add_event::<MouseOverEmpty>()
             .add_event::<MouseOverRegionEvent>()
             .add_event::<AtkMonsterWithPlayerSkill>()
+            .add_event::<DescendEvent>()
             .init_resource::<PlayerStatus>()
             .init_resource::<WorldMouse>()
             .init_resource::<Regions>()
             .init_resource::<RegionEntityMap>()
             .init_resource::<CurrentOverRegion>()
+            .init_resource::<CurrentFloor>()
+            .init_resource::<AutoReveal>()
             .add_system_set(SystemSet::on_enter(GameStage::Main).with_system(spawn_region_system))
             .add_system_set(
                 SystemSet::on_update(GameStage::Main)
@@ -52,22 +72,31 @@ impl Plugin for RegionPurePlugin {
                     .with_system(atk_monster)
                     .with_system(visit_region)
                     .with_system(visit_value_region)
+                    .with_system(visit_shop_region)
+                    .with_system(visit_trap_region)
                     .with_system(update_enemy_hp_system)
-                    .with_system(change_region_status_system),
+                    .with_system(change_region_status_system)
+                    .with_system(toggle_auto_reveal)
+                    .with_system(use_stairs)
+                    .with_system(descend_system)
+                    .with_system(defeat_boss_system),
             );
     }
 }
 
-const GEN_REGION_ITEMS: u64 = 32 * 32;
-
 impl Monster {
-    pub fn to_enemy_status(&self) -> EnemyStatus {
+    // scale 为所在层数的属性倍率
+    pub fn to_enemy_status(&self, scale: f64) -> EnemyStatus {
+        let hp = scale_i64(self.hp as i64, scale);
         EnemyStatus {
             name: self.name.clone(),
-            atk: self.atk,
-            def: self.def,
-            max_hp: self.hp,
-            cur_hp: self.hp as i64,
+            atk: scale_i64(self.atk, scale),
+            def: scale_i64(self.def, scale),
+            max_hp: hp as u64,
+            cur_hp: hp,
+            traits: self.traits.clone(),
+            xp: scale_i64(self.xp, scale),
+            gold: scale_i64(self.gold, scale),
         }
     }
 
@@ -81,19 +110,114 @@ impl Monster {
     }
 }
 
+// 每局开始时从第一层开始, 指定了关卡时使用关卡的布局
 pub fn spawn_region_system(
     mut commands: Commands,
     mut regions: ResMut<Regions>,
     mut region_entity_map: ResMut<RegionEntityMap>,
+    mut current_floor: ResMut<CurrentFloor>,
+    mut flags: ResMut<RegionFlags>,
+    run_seed: Res<RunSeed>,
+    selected_level: Res<SelectedLevel>,
+) {
+    *current_floor = CurrentFloor::default();
+    flags.0.clear();
+    spawn_floor(
+        &mut commands,
+        &mut regions,
+        &mut region_entity_map,
+        run_seed.floor_seed(current_floor.0),
+        current_floor.0,
+        selected_level.0.as_ref(),
+    );
+}
+
+fn insert_monster(commands: &mut Commands, entity: Entity, monster: &Monster, scale: f64) {
+    commands
+        .entity(entity)
+        .insert(monster.to_enemy_status(scale))
+        .insert(monster.to_enemy_label())
+        .insert(EnemyMark)
+        .insert(StatusEffects::default())
+        .insert(HasBattlePanel);
+}
+
+fn insert_value(commands: &mut Commands, entity: Entity, value: &Value, scale: f64) {
+    commands
+        .entity(entity)
+        .insert(value.scaled(scale))
+        .insert(HasValuePanel);
+}
+
+/*
+    生成一层地图并为每个方块创建实体
+    随机地图中离起始方块最远的房间放置通往下一层的楼梯, 最后一层则放置首领
+    关卡只有一层, 由关卡文件指定的内容代替楼梯与首领
+*/
+fn spawn_floor(
+    commands: &mut Commands,
+    regions: &mut Regions,
+    region_entity_map: &mut RegionEntityMap,
+    seed: u64,
+    floor: u32,
+    level: Option<&Level>,
 ) {
     let values_pool = get_values_pool();
-    let plane_orientation_pool = get_plane_orientation_pool();
+    let generator_config = get_generator_config();
     let monsters_pool = get_monsters_pool();
-    regions.clear();
-    regions.random_generate_tiles(GEN_REGION_ITEMS, &plane_orientation_pool);
+    let bosses_pool = get_bosses_pool();
+    let floor_config = get_floor_config();
+    let difficulty = get_difficulty_config();
+    let content_table = get_content_table();
+    // 每层开始时按种子重置随机数, 保证同一种子生成同样的地图与内容
+    let mut rand = RAND.lock().unwrap();
+    *rand = RngStreams::new(seed);
+    let connectivity = match level {
+        Some(level) => regions.generate(level, rand.stream(rng::LAYOUT)),
+        None => {
+            regions.scope = generator_config.scope;
+            regions.generate(&*generator_config.build(), rand.stream(rng::LAYOUT))
+        }
+    };
+    info!(
+        "floor {}: {} reachable, {} pruned, components {:?}",
+        floor,
+        connectivity.reachable,
+        connectivity.pruned.len(),
+        connectivity.components
+    );
+    let contents = level.map_or_else(HashMap::new, |level| level.contents(regions));
+    let farthest = match level {
+        Some(_) => None,
+        None => regions.farthest_exit(),
+    };
+    let (stairs, boss) = match floor_config.is_last(floor) {
+        true => (None, farthest),
+        false => (farthest, None),
+    };
+    let monster_scale = floor_config.monster_scale(floor);
+    let value_scale = floor_config.value_scale(floor);
+    // 区域的深度, 为到起始方块的步数与本层最大步数之比
+    let distances = match regions.start {
+        Some(start) => regions.distance_map(start),
+        None => Default::default(),
+    };
+    let max_distance = distances.values().copied().max().unwrap_or(0).max(1);
+    let depth_of = |id: u64| distances.get(&id).copied().unwrap_or(0) as f64 / max_distance as f64;
+    let find_monster = |name: &str| {
+        monsters_pool
+            .items()
+            .chain(bosses_pool.items())
+            .find(|monster| monster.name == name)
+            .expect("level monsters are checked in Level::load")
+    };
 
-    for (_, region) in regions.tiles.iter() {
-        let region_status: RegionStatus = match region.to_tile_type() {
+    region_entity_map.0.clear();
+    // 按id顺序遍历, 避免HashMap的遍历顺序打乱随机数的消耗顺序
+    let mut ids = regions.tiles.keys().copied().collect::<Vec<_>>();
+    ids.sort();
+    for region in ids.iter().map(|id| &regions.tiles[id]) {
+        let region_status: RegionStatus = match region.tile_type {
             TileType::Started => RegionStatus::Found,
             _ => RegionStatus::Mist,
         };
@@ -104,29 +228,146 @@ pub fn spawn_region_system(
             .id();
         region_entity_map.0.insert(region.id, entity);
 
-        if let TileType::Room = region.to_tile_type() {
-            let (_, is_gen_monsters) = RAND.lock().unwrap().random_val_boolean(0.7);
-            if is_gen_monsters {
-                let monster = monsters_pool.fetch_item();
-                commands
-                    .entity(entity)
-                    .insert(monster.to_enemy_status())
-                    .insert(monster.to_enemy_label())
-                    .insert(EnemyMark)
-                    .insert(HasBattlePanel);
-            } else {
-                let value = values_pool.fetch_item();
-                commands
-                    .entity(entity)
-                    .insert(value.clone())
-                    .insert(HasValuePanel);
+        if stairs == Some(region.id) {
+            commands.entity(entity).insert(StairsMark);
+            continue;
+        }
+        if boss == Some(region.id) {
+            // 首领的属性不随层数缩放
+            let monster = bosses_pool.fetch_item(rand.stream(rng::CONTENT));
+            insert_monster(commands, entity, monster, 1.);
+            commands.entity(entity).insert(BossMark);
+            continue;
+        }
+        if let Some(content) = contents.get(&region.id) {
+            match content {
+                Content::Monster(name) => {
+                    insert_monster(commands, entity, find_monster(name), monster_scale)
+                }
+                Content::Boss(name) => {
+                    insert_monster(commands, entity, find_monster(name), 1.);
+                    commands.entity(entity).insert(BossMark);
+                }
+                Content::Value(name) => {
+                    let value = values_pool
+                        .items()
+                        .find(|value| &value.name == name)
+                        .expect("level values are checked in Level::load");
+                    insert_value(commands, entity, value, value_scale);
+                }
+                Content::Empty => (),
+            }
+            continue;
+        }
+
+        let depth = depth_of(region.id);
+        let content = content_table.pool(region.tile_type, region.shape.len());
+        match content.fetch_item(rand.stream(rng::CONTENT)) {
+            ContentKind::Monster => {
+                let monster = monsters_pool
+                    .fetch_item_by(rand.stream(rng::CONTENT), |monster, weight| {
+                        weight * difficulty.monster_weight(&monster.name, depth)
+                    });
+                insert_monster(
+                    commands,
+                    entity,
+                    monster,
+                    monster_scale * difficulty.monster_stats(depth),
+                );
             }
+            ContentKind::Value => {
+                let value = values_pool.fetch_item_by(rand.stream(rng::LOOT), |value, weight| {
+                    weight * difficulty.value_weight(&value.name, depth)
+                });
+                insert_value(commands, entity, value, value_scale);
+            }
+            ContentKind::Trap => {
+                let damage = content_table.trap_damage.to_i64(rand.stream(rng::LOOT));
+                commands.entity(entity).insert(Trap {
+                    damage: scale_i64(damage, monster_scale),
+                });
+            }
+            ContentKind::Shop => {
+                let value = values_pool.fetch_item_by(rand.stream(rng::LOOT), |value, weight| {
+                    weight * difficulty.value_weight(&value.name, depth)
+                });
+                let price = content_table.shop_price.to_i64(rand.stream(rng::LOOT));
+                insert_value(commands, entity, value, value_scale);
+                commands.entity(entity).insert(Shop {
+                    price: scale_i64(price, value_scale),
+                });
+            }
+            ContentKind::Empty => (),
+        }
+    }
+}
+
+// 点击已探索过的楼梯时前往下一层
+pub fn use_stairs(
+    query: Query<(&RegionId, &RegionStatus), With<StairsMark>>,
+    mut trigger_region_event: EventReader<RegionClickEvent>,
+    mut descend_event: EventWriter<DescendEvent>,
+) {
+    for RegionClickEvent(id) in trigger_region_event.iter() {
+        for (RegionId(region_id), region_status) in query.iter() {
+            if region_id == id && *region_status == RegionStatus::Visited {
+                descend_event.send(DescendEvent);
+            }
+        }
+    }
+}
+
+/*
+    销毁当前层的所有方块实体并生成下一层, PlayerStatus 与 PlayerSkills 保持不变
+*/
+pub fn descend_system(
+    mut commands: Commands,
+    mut descend_event: EventReader<DescendEvent>,
+    query: Query<Entity, (With<RegionId>, Without<Parent>)>,
+    mut regions: ResMut<Regions>,
+    mut region_entity_map: ResMut<RegionEntityMap>,
+    mut current_floor: ResMut<CurrentFloor>,
+    mut current_over_region: ResMut<CurrentOverRegion>,
+    mut flags: ResMut<RegionFlags>,
+    run_seed: Res<RunSeed>,
+) {
+    if descend_event.iter().count() == 0 {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *current_over_region = CurrentOverRegion::None;
+    flags.0.clear();
+    current_floor.0 += 1;
+    spawn_floor(
+        &mut commands,
+        &mut regions,
+        &mut region_entity_map,
+        run_seed.floor_seed(current_floor.0),
+        current_floor.0,
+        None,
+    );
+}
+
+// 击败首领即通关, 玩家与首领同归于尽时按失败处理
+pub fn defeat_boss_system(
+    query: Query<&EnemyStatus, (With<BossMark>, Changed<EnemyStatus>)>,
+    player_status: Res<PlayerStatus>,
+    mut game_stage: ResMut<State<GameStage>>,
+) {
+    for enemy in query.iter() {
+        if enemy.cur_hp <= 0 && player_status.cur_hp > 0 {
+            // 同一帧里已有状态切换在排队时(失败或升级)忽略, 与 start_level_up 一致
+            let _ = game_stage.set(GameStage::Victory);
+            return;
         }
     }
 }
 
+// 只有点击有怪物的区域才算一个回合, 宝物 商店与陷阱不推进技能轮转与状态效果
 pub fn atk_monster(
-    query: Query<(&RegionId, &RegionStatus)>,
+    query: Query<(&RegionId, &RegionStatus), With<EnemyMark>>,
     mut trigger_region_event: EventReader<RegionClickEvent>,
     mut atk_monster_with_player_skill: EventWriter<AtkMonsterWithPlayerSkill>,
 ) {
@@ -141,8 +382,9 @@ pub fn atk_monster(
     }
 }
 
+// 商店只在购买成功后才算访问, 见 visit_shop_region
 pub fn visit_region(
-    query: Query<(&RegionId, &RegionStatus), Without<EnemyMark>>,
+    query: Query<(&RegionId, &RegionStatus), (Without<EnemyMark>, Without<Shop>)>,
     mut trigger_region_event: EventReader<RegionClickEvent>,
     mut change_region_status_event: EventWriter<ChangeRegionStatusEvent>,
 ) {
@@ -156,43 +398,90 @@ pub fn visit_region(
     }
 }
 
+fn apply_value(value: &Value, player_status: &mut PlayerStatus, effects: &mut StatusEffects) {
+    let mut rand = RAND.lock().unwrap();
+    let loot = rand.stream(rng::LOOT);
+    for value in value.values.iter() {
+        match value {
+            KeyValue::PlayerCurrentHp(val) => {
+                player_status.cur_hp =
+                    (player_status.cur_hp + val.to_i64(loot)).min(player_status.max_hp);
+            }
+            KeyValue::PlayerAtk(val) => player_status.atk += val.to_i64(loot),
+            KeyValue::PlayerDef(val) => player_status.def += val.to_i64(loot),
+            KeyValue::PlayerMaxHp(val) => player_status.max_hp += val.to_i64(loot),
+            KeyValue::PlayerGold(val) => player_status.gold += val.to_i64(loot),
+            KeyValue::PlayerEffect(effect) => effects.add(*effect),
+        }
+    }
+}
+
 pub fn visit_value_region(
-    query: Query<(&RegionId, &RegionStatus, &Value)>,
+    query: Query<(&RegionId, &RegionStatus, &Value), Without<Shop>>,
     mut trigger_region_event: EventReader<RegionClickEvent>,
     mut player_status: ResMut<PlayerStatus>,
+    mut effects: ResMut<StatusEffects>,
 ) {
     for RegionClickEvent(id) in trigger_region_event.iter() {
         for (RegionId(region_id), region_status, value) in query.iter() {
             if region_id == id && *region_status == RegionStatus::Found {
-                for value in value.values.iter() {
-                    match value {
-                        crate::pool::values::KeyValue::PlayerCurrentHp(val) => {
-                            player_status.cur_hp =
-                                (player_status.cur_hp + val.to_i64()).min(player_status.max_hp);
-                        }
-                        crate::pool::values::KeyValue::PlayerAtk(val) => {
-                            player_status.atk += val.to_i64()
-                        }
-                        crate::pool::values::KeyValue::PlayerDef(val) => {
-                            player_status.def += val.to_i64()
-                        }
-                        crate::pool::values::KeyValue::PlayerMaxHp(val) => {
-                            player_status.max_hp += val.to_i64()
-                        }
-                        crate::pool::values::KeyValue::PlayerGold(val) => {
-                            player_status.gold += val.to_i64()
-                        }
-                    }
-                }
+                apply_value(value, &mut player_status, &mut effects);
+            }
+        }
+    }
+}
+
+/*
+    金币足够时购买商店中的物品并访问该区域
+    金币不足时商店保持 Found, 之后可以带着足够的金币回来购买
+*/
+pub fn visit_shop_region(
+    query: Query<(&RegionId, &RegionStatus, &Value, &Shop)>,
+    mut trigger_region_event: EventReader<RegionClickEvent>,
+    mut change_region_status_event: EventWriter<ChangeRegionStatusEvent>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut effects: ResMut<StatusEffects>,
+) {
+    for RegionClickEvent(id) in trigger_region_event.iter() {
+        for (RegionId(region_id), region_status, value, shop) in query.iter() {
+            if region_id == id
+                && *region_status == RegionStatus::Found
+                && player_status.gold >= shop.price
+            {
+                player_status.gold -= shop.price;
+                apply_value(value, &mut player_status, &mut effects);
+                change_region_status_event
+                    .send(ChangeRegionStatusEvent(*region_id, RegionStatus::Mist));
             }
         }
     }
 }
 
+// 踏入陷阱时受到伤害
+pub fn visit_trap_region(
+    query: Query<(&RegionId, &RegionStatus, &Trap)>,
+    mut trigger_region_event: EventReader<RegionClickEvent>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut play_audio_event: EventWriter<PlayAudioEvent>,
+) {
+    for RegionClickEvent(id) in trigger_region_event.iter() {
+        for (RegionId(region_id), region_status, trap) in query.iter() {
+            if region_id == id && *region_status == RegionStatus::Found {
+                player_status.cur_hp -= trap.damage;
+                play_audio_event.send(PlayAudioEvent(AudioSound::Dao5));
+            }
+        }
+    }
+}
+
+// 击败怪物时获得其经验与金币, 持续伤害与自爆击倒的也算在内
 pub fn update_enemy_hp_system(
     mut query: Query<(&mut EnemyStatus, &RegionId)>,
     mut change_enemy_hp_event: EventReader<ChangeEnemyHpEvent>,
     mut change_region_status_event: EventWriter<ChangeRegionStatusEvent>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut pending_level_ups: ResMut<PendingLevelUps>,
+    experience_config: Res<ExperienceConfig>,
 ) {
     for ChangeEnemyHpEvent(id, val) in change_enemy_hp_event.iter() {
         for (mut enemy, RegionId(region_id)) in &mut query.iter_mut() {
@@ -201,61 +490,102 @@ pub fn update_enemy_hp_system(
                 if enemy.cur_hp <= 0 {
                     change_region_status_event
                         .send(ChangeRegionStatusEvent(*id, RegionStatus::Mist));
+                    player_status.gold += enemy.gold;
+                    pending_level_ups.0 += experience_config.gain_xp(&mut player_status, enemy.xp);
                 }
             }
         }
     }
 }
 
+fn toggle_auto_reveal(keys: Res<Input<KeyCode>>, mut auto_reveal: ResMut<AutoReveal>) {
+    if keys.just_pressed(KeyCode::R) {
+        auto_reveal.0 = !auto_reveal.0;
+    }
+}
+
+/*
+    将 Found 的区域标记为 Visited 并揭开其相邻的区域
+    开启 AutoReveal 时, 从访问的空区域出发连带访问相连的未访问空区域, 直到遇到有内容的区域
+*/
 pub fn change_region_status_system(
     mut commands: Commands,
     mut change_region_status_event: EventReader<ChangeRegionStatusEvent>,
-    regions: ResMut<Regions>,
-    mut sprite_query: Query<(Entity, &RegionId, &RegionStatus), With<RegionMark>>,
+    regions: Res<Regions>,
+    auto_reveal: Res<AutoReveal>,
+    flags: Res<RegionFlags>,
+    sprite_query: Query<(Entity, &RegionId, &RegionStatus), With<RegionMark>>,
+    content_query: Query<
+        &RegionId,
+        Or<(
+            With<EnemyMark>,
+            With<Value>,
+            With<Trap>,
+            With<Shop>,
+            With<StairsMark>,
+        )>,
+    >,
     mut visible_query: Query<(&mut Visibility, &RegionId)>,
     mut play_audio_event: EventWriter<PlayAudioEvent>,
 ) {
-    let mut found_tiles = Vec::<&Tile>::new();
-    for ev in change_region_status_event.iter() {
-        let ChangeRegionStatusEvent(entity, ..) = ev;
-        for (en, RegionId(region_id), status) in sprite_query.iter_mut() {
-            match status {
-                RegionStatus::Found => {
-                    if region_id == entity {
-                        commands.entity(en).insert(RegionStatus::Visited);
-                        play_audio_event.send(PlayAudioEvent(AudioSound::Click));
-                        if let Some(tile) = regions.tiles.get(region_id) {
-                            found_tiles.push(&tile);
-                        }
-                    }
-                }
-                _ => (),
-            }
+    let statuses = sprite_query
+        .iter()
+        .map(|(en, RegionId(id), status)| (*id, (en, *status)))
+        .collect::<HashMap<_, _>>();
+    let is_status =
+        |id: &u64, status: RegionStatus| matches!(statuses.get(id), Some((_, s)) if *s == status);
+
+    let mut visited = vec![];
+    for ChangeRegionStatusEvent(id, ..) in change_region_status_event.iter() {
+        if is_status(id, RegionStatus::Found) && !visited.contains(id) {
+            visited.push(*id);
         }
     }
+    if visited.is_empty() {
+        return;
+    }
 
-    for tile in found_tiles {
-        for tile_id in tile.adjacent.clone().into_iter() {
-            for (en, RegionId(id), status, ..) in sprite_query.iter_mut() {
-                match status {
-                    RegionStatus::Mist => {
-                        if *id == tile_id {
-                            // HPText
-                            commands.entity(en).insert(RegionStatus::Found);
-                        }
-                    }
-                    _ => (),
-                }
+    if auto_reveal.0 {
+        let contents = content_query
+            .iter()
+            .map(|RegionId(id)| *id)
+            .collect::<HashSet<_>>();
+        let from = visited
+            .iter()
+            .filter(|id| !contents.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        // 玩家标记为危险的区域不会被连带访问
+        visited.extend(regions.flood(&from, |id| {
+            !contents.contains(&id)
+                && !is_status(&id, RegionStatus::Visited)
+                && !flags.is_danger(id)
+        }));
+    }
+
+    // 连带访问的区域也只播放一次音效
+    play_audio_event.send(PlayAudioEvent(AudioSound::Click));
+    for id in visited.iter() {
+        commands
+            .entity(statuses[id].0)
+            .insert(RegionStatus::Visited);
+    }
+    for id in visited.iter() {
+        let tile = match regions.tiles.get(id) {
+            Some(tile) => tile,
+            None => continue,
+        };
+        for tile_id in tile.adjacent.iter() {
+            if is_status(tile_id, RegionStatus::Mist) && !visited.contains(tile_id) {
+                commands
+                    .entity(statuses[tile_id].0)
+                    .insert(RegionStatus::Found);
             }
         }
-        for tile_id in tile.adjacent.clone().into_iter() {
-            for (mut visibility, RegionId(region_id), ..) in visible_query.iter_mut() {
-                if tile_id == *region_id {
-                    visibility.is_visible = true;
-                }
+        for (mut visibility, RegionId(region_id)) in visible_query.iter_mut() {
+            if tile.adjacent.contains(region_id) {
+                visibility.is_visible = true;
             }
         }
     }
-
-    // hp_text_query
 }
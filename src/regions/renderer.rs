@@ -1,12 +1,19 @@
 use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioControl};
+use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*};
 
 use crate::camera::SceneCamera;
-use crate::marks::{EnemyLabel, EnemyMark, RegionId, RegionRect, ValueText};
+use crate::marks::{
+    BossMark, EnemyLabel, EnemyMark, FlagText, HintText, RegionId, RegionRect, Shop, ShopText,
+    StairsMark, StairsText, Trap, TrapText, ValueText,
+};
 use crate::pool::values::Value;
 use crate::{AudioAssets, GameStage};
 
 use super::events::{MouseOverEmpty, MouseOverRegionEvent, PlayAudioEvent};
+use super::flags::{RegionFlag, RegionFlags};
+use super::geometry::{cell_rects, tile_at};
+use super::manager::Tile;
 use super::RegionClickEvent;
 use super::{pure::RegionMark, Regions};
 
@@ -16,12 +23,12 @@ use bevy::prelude::Transform;
 
 pub struct RegionRenderPlugin;
 
-const SIZE: f32 = 32.;
-const GAP: f32 = 4.;
+pub(super) const SIZE: f32 = 32.;
+pub(super) const GAP: f32 = 4.;
 
 impl Plugin for RegionRenderPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app
+        app.add_plugin(ShapePlugin)
             // .add_system_set(SystemSet::on_enter(GameStage::Main).with_system(setup))
             .add_system_set(
                 SystemSet::on_update(GameStage::Main)
@@ -34,11 +41,64 @@ impl Plugin for RegionRenderPlugin {
                     // .with_system(fill_enemy_text_system)
                     .with_system(fill_value_text_system)
                     .with_system(update_enemy_hp_text_system)
-                    .with_system(region_rect_color_system),
+                    .with_system(region_rect_color_system)
+                    .with_system(update_flag_text_system),
             );
     }
 }
 
+/*
+    按方块实际的格子形状构建网格, translation 为网格的原点
+*/
+fn tile_shape_bundle(tile: &Tile, translation: Vec3, color: Color) -> ShapeBundle {
+    let origin = translation.truncate();
+    let mut builder = GeometryBuilder::new();
+    for rect in cell_rects(&tile.shape, SIZE, GAP) {
+        builder = builder.add(&shapes::Rectangle {
+            extents: rect.size(),
+            origin: RectangleOrigin::CustomCenter(rect.center() - origin),
+        });
+    }
+    builder.build(
+        DrawMode::Fill(FillMode::color(color)),
+        Transform::from_translation(translation),
+    )
+}
+
+// 仅在颜色变化时修改 DrawMode, 避免每帧重新生成网格
+fn set_fill_color(draw_mode: &mut Mut<DrawMode>, color: Color) {
+    let current = match **draw_mode {
+        DrawMode::Fill(FillMode { color, .. }) => Some(color),
+        _ => None,
+    };
+    if current != Some(color) {
+        **draw_mode = DrawMode::Fill(FillMode::color(color));
+    }
+}
+
+// 区域上默认隐藏的说明文字
+fn label_text_bundle(
+    asset_server: &AssetServer,
+    label: String,
+    color: Color,
+    pos: Vec2,
+) -> Text2dBundle {
+    Text2dBundle {
+        text: Text::from_section(
+            label,
+            TextStyle {
+                font: asset_server.load("fonts/hanti.ttf"),
+                font_size: 16.0,
+                color,
+            },
+        )
+        .with_alignment(TextAlignment::CENTER),
+        visibility: Visibility { is_visible: false },
+        transform: Transform::from_translation(pos.extend(2.)),
+        ..Default::default()
+    }
+}
+
 fn spawn_region_rect(
     mut commands: Commands,
     regions: ResMut<Regions>,
@@ -49,43 +109,55 @@ fn spawn_region_rect(
             Option<&EnemyMark>,
             Option<&Value>,
             Option<&EnemyLabel>,
+            Option<&StairsMark>,
+            Option<&BossMark>,
+            Option<&Trap>,
+            Option<&Shop>,
         ),
         Added<RegionMark>,
     >,
 ) {
-    for (RegionId(region_id), _, value, label) in query.iter() {
+    for (RegionId(region_id), _, value, label, stairs, boss, trap, shop) in query.iter() {
         if let Some(tile) = regions.tiles.get(region_id) {
             let transform = tile.to_transform(SIZE, GAP).unwrap();
             let region_id = RegionId(tile.id);
 
             // region rect
             commands
-                .spawn(SpriteBundle::default())
-                .insert(Sprite {
-                    color: Color::NONE,
-                    ..default()
-                })
-                .insert(transform)
+                .spawn(tile_shape_bundle(
+                    tile,
+                    Vec3::new(transform.translation.x, transform.translation.y, 0.),
+                    Color::NONE,
+                ))
                 .insert(RegionRect)
-                .insert(region_id);
+                .insert(region_id)
+                .with_children(|parent| {
+                    // 危险提示, 由 hints 模块填写并控制显示
+                    parent
+                        .spawn(Text2dBundle {
+                            visibility: Visibility { is_visible: false },
+                            transform: Transform::from_xyz(0., 10., 3.),
+                            ..Default::default()
+                        })
+                        .insert(HintText(tile.id));
+                    // 玩家的标记
+                    parent
+                        .spawn(Text2dBundle {
+                            visibility: Visibility { is_visible: false },
+                            transform: Transform::from_xyz(0., -10., 3.),
+                            ..Default::default()
+                        })
+                        .insert(FlagText(tile.id));
+                });
 
             if let Some(label) = label {
                 //  enemy hp color
                 commands
-                    .spawn(SpriteBundle::default())
-                    .insert(Transform {
-                        translation: Vec3::new(
-                            transform.translation.x,
-                            transform.translation.y,
-                            1.,
-                        ),
-                        scale: transform.scale,
-                        ..Default::default()
-                    })
-                    .insert(Sprite {
-                        color: Color::rgba_u8(255, 0, 0, 255),
-                        ..Default::default()
-                    })
+                    .spawn(tile_shape_bundle(
+                        tile,
+                        Vec3::new(transform.translation.x, transform.translation.y, 1.),
+                        Color::rgba_u8(255, 0, 0, 255),
+                    ))
                     .insert(HPColor)
                     .insert(Visibility { is_visible: false })
                     .insert(region_id);
@@ -108,6 +180,7 @@ fn spawn_region_rect(
                         custom_size: Some(Vec2::new(transform.scale.x, transform.scale.y)),
                         ..default()
                     })
+                    .insert(region_id)
                     .with_children(|parent| {
                         // text
                         parent
@@ -125,7 +198,11 @@ fn spawn_region_rect(
                         parent
                             .spawn(SpriteBundle {
                                 sprite: Sprite {
-                                    color: Color::GOLD,
+                                    // 首领的图标使用红色
+                                    color: match boss {
+                                        Some(_) => Color::RED,
+                                        None => Color::GOLD,
+                                    },
                                     custom_size: Some(Vec2::new(16., 16.)),
                                     ..default()
                                 },
@@ -158,6 +235,57 @@ fn spawn_region_rect(
                     .insert(ValueText)
                     .insert(region_id);
             }
+
+            if stairs.is_some() {
+                commands
+                    .spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "梯",
+                            TextStyle {
+                                font: asset_server.load("fonts/hanti.ttf"),
+                                font_size: 24.0,
+                                color: Color::GOLD,
+                            },
+                        )
+                        .with_alignment(TextAlignment::CENTER),
+                        visibility: Visibility { is_visible: false },
+                        transform: Transform {
+                            translation: Vec3::new(
+                                transform.translation.x,
+                                transform.translation.y,
+                                2.,
+                            ),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .insert(StairsText)
+                    .insert(region_id);
+            }
+
+            // 陷阱显示伤害, 商店显示价格, 价格位于物品名称下方
+            if let Some(trap) = trap {
+                commands
+                    .spawn(label_text_bundle(
+                        &asset_server,
+                        format!("阱 {}", trap.damage),
+                        Color::MAROON,
+                        transform.translation.truncate(),
+                    ))
+                    .insert(TrapText)
+                    .insert(region_id);
+            }
+            if let Some(shop) = shop {
+                commands
+                    .spawn(label_text_bundle(
+                        &asset_server,
+                        format!("价 {}", shop.price),
+                        Color::OLIVE,
+                        transform.translation.truncate() - Vec2::new(0., 16.),
+                    ))
+                    .insert(ShopText)
+                    .insert(region_id);
+            }
         }
     }
 }
@@ -223,7 +351,7 @@ fn fill_value_text_system(
 fn update_enemy_hp_text_system(
     mut query: Query<(&mut Text, &RegionId), With<EnemyText>>,
     query_enemy: Query<(&EnemyStatus, &RegionId), Changed<EnemyStatus>>,
-    mut query_color: Query<(&mut Sprite, &RegionId), With<HPColor>>,
+    mut query_color: Query<(&mut DrawMode, &RegionId), With<HPColor>>,
 ) {
     for (enemy, RegionId(region_id)) in &mut query_enemy.iter() {
         for (mut text, RegionId(id)) in &mut query.iter_mut() {
@@ -231,9 +359,12 @@ fn update_enemy_hp_text_system(
                 if text.sections.len() != 0 {
                     text.sections[0].value = format!("\n{}/{}", enemy.cur_hp, enemy.max_hp);
                 }
-                for (mut sp, RegionId(region_id)) in &mut query_color.iter_mut() {
+                for (mut draw_mode, RegionId(region_id)) in &mut query_color.iter_mut() {
                     if region_id == id {
-                        sp.color.set_a(enemy.cur_hp as f32 / enemy.max_hp as f32);
+                        set_fill_color(
+                            &mut draw_mode,
+                            Color::rgba(1., 0., 0., enemy.cur_hp as f32 / enemy.max_hp as f32),
+                        );
                     }
                 }
             }
@@ -246,23 +377,46 @@ fn region_rect_color_system(
         (&RegionId, &RegionStatus),
         // Or<(Changed<RegionStatus>, Added<RegionStatus>)>,
     >,
-    mut region_react_query: Query<(&RegionId, &mut Sprite), With<RegionRect>>,
+    mut region_react_query: Query<(&RegionId, &mut DrawMode), With<RegionRect>>,
 ) {
     for (RegionId(region_id), region_status) in region_status_query.iter() {
-        for (RegionId(id), mut sprite) in region_react_query.iter_mut() {
+        for (RegionId(id), mut draw_mode) in region_react_query.iter_mut() {
             if region_id == id {
-                match region_status {
-                    RegionStatus::Mist => {
-                        sprite.color = Color::BLACK;
-                    }
-                    RegionStatus::Found => {
-                        sprite.color = Color::GREEN;
-                    }
-                    RegionStatus::Visited => {
-                        sprite.color = Color::GRAY;
-                    }
+                let color = match region_status {
+                    RegionStatus::Mist => Color::BLACK,
+                    RegionStatus::Found => Color::GREEN,
+                    RegionStatus::Visited => Color::GRAY,
                 };
+                set_fill_color(&mut draw_mode, color);
+            }
+        }
+    }
+}
+
+fn update_flag_text_system(
+    asset_server: Res<AssetServer>,
+    flags: Res<RegionFlags>,
+    mut query: Query<(&FlagText, &mut Text, &mut Visibility)>,
+    added: Query<(), Added<FlagText>>,
+) {
+    if !flags.is_changed() && added.is_empty() {
+        return;
+    }
+    for (FlagText(id), mut text, mut visibility) in query.iter_mut() {
+        match flags.get(*id) {
+            Some(flag) => {
+                *text = Text::from_section(
+                    flag.label(),
+                    TextStyle {
+                        font: asset_server.load("fonts/hanti.ttf"),
+                        font_size: 14.0,
+                        color: flag.color(),
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER);
+                visibility.is_visible = true;
             }
+            None => visibility.is_visible = false,
         }
     }
 }
@@ -271,33 +425,30 @@ fn mouse_interaction(
     windows: Res<Windows>,
     buttons: Res<Input<MouseButton>>,
     q_camera: Query<&Transform, With<SceneCamera>>,
-    q_regions: Query<(&RegionId, &Transform), With<Sprite>>,
+    regions: Res<Regions>,
+    mut flags: ResMut<RegionFlags>,
 
     mut trigger_region_event: EventWriter<RegionClickEvent>,
     mut mouse_over_region: EventWriter<MouseOverRegionEvent>,
     mut mouse_over_empty: EventWriter<MouseOverEmpty>,
 ) {
-    let mut ev = None;
-    if let Some(wnd) = windows.get_primary() {
-        if let Some(pos) = wnd.cursor_position() {
-            let size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-            let p = pos - size / 2.0;
-            if let Ok(camera_transform) = q_camera.get_single() {
-                let pos_wld = camera_transform.compute_matrix() * p.extend(0.0).extend(1.0);
-                for (RegionId(id), region) in q_regions.iter() {
-                    let dx = (pos_wld.x - region.translation.x).abs();
-                    let dy = (pos_wld.y - region.translation.y).abs();
-                    if dx <= region.scale.x / 2. && dy <= region.scale.y / 2. {
-                        ev = Some(MouseOverRegionEvent(*id))
-                    }
-                }
+    let ev = cursor_world_position(windows.get_primary(), &q_camera)
+        .and_then(|pos| tile_at(&regions.tile_map, pos.truncate(), SIZE, GAP))
+        .map(MouseOverRegionEvent);
+
+    if buttons.just_pressed(MouseButton::Left) {
+        if let Some(MouseOverRegionEvent(id)) = ev {
+            // 标记为危险的区域不响应点击, 避免误入
+            if !flags.is_danger(id) {
+                trigger_region_event.send(RegionClickEvent(id));
             }
         }
     }
 
-    if buttons.just_pressed(MouseButton::Left) {
+    if buttons.just_pressed(MouseButton::Right) {
         if let Some(MouseOverRegionEvent(id)) = ev {
-            trigger_region_event.send(RegionClickEvent(id));
+            let flag = RegionFlag::cycle(flags.get(id));
+            flags.set(id, flag);
         }
     }
 
@@ -311,23 +462,27 @@ fn mouse_interaction(
 #[derive(Resource, Default)]
 pub struct WorldMouse(pub Vec3);
 
+// 鼠标在场景中的世界坐标
+fn cursor_world_position(
+    wnd: Option<&Window>,
+    q_camera: &Query<&Transform, With<SceneCamera>>,
+) -> Option<Vec3> {
+    let wnd = wnd?;
+    let pos = wnd.cursor_position()?;
+    let size = Vec2::new(wnd.width(), wnd.height());
+    let p = pos - size / 2.0;
+    let camera_transform = q_camera.get_single().ok()?;
+    let pos_wld = camera_transform.compute_matrix() * p.extend(0.0).extend(1.0);
+    Some(Vec3::new(pos_wld.x, pos_wld.y, pos_wld.z))
+}
+
 fn current_world_mouse(
-    mut windows: ResMut<Windows>,
+    windows: Res<Windows>,
     q_camera: Query<&Transform, With<SceneCamera>>,
     mut commands: Commands,
 ) {
-    if let Some(wnd) = windows.get_primary_mut() {
-        // if wnd.cursor_visible() {
-        //     wnd.set_cursor_visibility(false);
-        // }
-        if let Some(pos) = wnd.cursor_position() {
-            let size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-            let p = pos - size / 2.0;
-            if let Ok(camera_transform) = q_camera.get_single() {
-                let pos_wld = camera_transform.compute_matrix() * p.extend(0.0).extend(1.0);
-                commands.insert_resource(WorldMouse(Vec3::new(pos_wld.x, pos_wld.y, pos_wld.z)));
-            }
-        }
+    if let Some(pos) = cursor_world_position(windows.get_primary(), &q_camera) {
+        commands.insert_resource(WorldMouse(pos));
     }
 }
 
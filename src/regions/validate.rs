@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::components::TileType;
+use crate::pool::terrains::{PlaneOrientation, Point};
+
+use super::manager::{Connectivity, Regions};
+
+// 地图数据违反的约束
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    MissingStart,                 // 没有起始方块或起始方块不存在
+    Overlap(Point, u64, u64),     // 同一个格子被两个方块占用
+    UnmappedCell(u64, Point),     // 方块的格子没有写入 tile_map
+    DanglingCell(Point, u64),     // tile_map 中的格子指向不存在或不包含该格子的方块
+    OutOfScope(u64, Point),       // 格子超出 scope
+    SplitShape(u64),              // 方块自身的格子不连通
+    UnknownAdjacent(u64, u64),    // adjacent 中包含不存在的方块
+    AsymmetricAdjacent(u64, u64), // a 记录了 b 相邻, b 没有记录 a
+    FalseAdjacent(u64, u64),      // 记录为相邻但实际并不接触
+    MissingAdjacent(u64, u64),    // 实际接触但没有记录为相邻
+    Unreachable(u64),             // 与起始方块不连通
+}
+
+// 单张地图的统计数据
+#[derive(Debug, Clone, Default)]
+pub struct MapStats {
+    pub tiles: usize,
+    pub rooms: usize,
+    pub obstacles: usize,
+    pub starts: usize,
+    pub corridors: usize,
+    pub specials: usize, // 神龛与宝库
+    pub cells: usize,
+    pub pruned: usize,
+    pub components: Vec<usize>, // 删除前各连通分量的大小, 从大到小
+    pub diameter: usize,        // 起始方块到最远方块的步数
+    pub no_room: usize,         // 找不到空位而被丢弃的方块
+    pub already_placed: usize,  // 重复放置而被丢弃的方块
+}
+
+impl MapStats {
+    pub fn collect(regions: &Regions, connectivity: &Connectivity) -> Self {
+        let mut stats = MapStats {
+            tiles: regions.tiles.len(),
+            cells: regions.tile_map.len(),
+            pruned: connectivity.pruned.len(),
+            components: connectivity.components.clone(),
+            no_room: regions.failures.no_room,
+            already_placed: regions.failures.already_placed,
+            ..Default::default()
+        };
+        for tile in regions.tiles.values() {
+            match tile.tile_type {
+                TileType::Room => stats.rooms += 1,
+                TileType::Obstacle => stats.obstacles += 1,
+                TileType::Started => stats.starts += 1,
+                TileType::Corridor => stats.corridors += 1,
+                TileType::Shrine | TileType::Vault => stats.specials += 1,
+            }
+        }
+        if let Some(start) = regions.start {
+            stats.diameter = regions
+                .distance_map(start)
+                .values()
+                .copied()
+                .max()
+                .unwrap_or(0);
+        }
+        stats
+    }
+}
+
+impl Regions {
+    /*
+        检查 tile_map / tiles / adjacent 之间的一致性, 返回所有违反的约束
+    */
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut ids = self.tiles.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+
+        match self.start {
+            Some(start) if self.tiles.contains_key(&start) => {
+                let reachable = self.distance_map(start);
+                for id in ids.iter() {
+                    if !reachable.contains_key(id) {
+                        violations.push(Violation::Unreachable(*id));
+                    }
+                }
+            }
+            _ => violations.push(Violation::MissingStart),
+        }
+
+        // 格子的归属
+        let mut owners: HashMap<Point, u64> = HashMap::new();
+        for id in ids.iter() {
+            let tile = &self.tiles[id];
+            let mut cells = tile.shape.iter().copied().collect::<Vec<_>>();
+            cells.sort_by_key(|Point(x, y)| (*x, *y));
+            for cell in cells {
+                if let Some(owner) = owners.insert(cell, *id) {
+                    violations.push(Violation::Overlap(cell, owner, *id));
+                }
+                if self.tile_map.get(&cell) != Some(id) {
+                    violations.push(Violation::UnmappedCell(*id, cell));
+                }
+                if !cell.in_scope(self.scope) {
+                    violations.push(Violation::OutOfScope(*id, cell));
+                }
+            }
+        }
+        let mut mapped = self.tile_map.iter().collect::<Vec<_>>();
+        mapped.sort_by_key(|(Point(x, y), _)| (*x, *y));
+        for (cell, id) in mapped {
+            match self.tiles.get(id) {
+                Some(tile) if tile.shape.contains(cell) => (),
+                _ => violations.push(Violation::DanglingCell(*cell, *id)),
+            }
+        }
+
+        for id in ids.iter() {
+            let tile = &self.tiles[id];
+
+            // 方块自身的连通性
+            if let Some(first) = tile.shape.iter().next() {
+                let mut visited = vec![*first];
+                let mut i = 0;
+                while i < visited.len() {
+                    for step in PlaneOrientation::all() {
+                        let next = visited[i] + PlaneOrientation::to_axis_direction(&step);
+                        if tile.shape.contains(&next) && !visited.contains(&next) {
+                            visited.push(next);
+                        }
+                    }
+                    i += 1;
+                }
+                if visited.len() != tile.shape.len() {
+                    violations.push(Violation::SplitShape(*id));
+                }
+            }
+
+            // 实际接触的方块
+            let mut touching = tile
+                .shape
+                .iter()
+                .flat_map(|p| {
+                    PlaneOrientation::all()
+                        .into_iter()
+                        .map(move |step| *p + PlaneOrientation::to_axis_direction(&step))
+                })
+                .filter_map(|p| self.tile_map.get(&p).copied())
+                .filter(|other| other != id)
+                .collect::<Vec<_>>();
+            touching.sort();
+            touching.dedup();
+
+            let mut adjacent = tile.adjacent.iter().copied().collect::<Vec<_>>();
+            adjacent.sort();
+            for other in adjacent.iter() {
+                match self.tiles.get(other) {
+                    None => violations.push(Violation::UnknownAdjacent(*id, *other)),
+                    Some(other_tile) => {
+                        if !other_tile.adjacent.contains(id) {
+                            violations.push(Violation::AsymmetricAdjacent(*id, *other));
+                        }
+                        if touching.binary_search(other).is_err() {
+                            violations.push(Violation::FalseAdjacent(*id, *other));
+                        }
+                    }
+                }
+            }
+            for other in touching {
+                if !tile.adjacent.contains(&other) {
+                    violations.push(Violation::MissingAdjacent(*id, other));
+                }
+            }
+        }
+        violations
+    }
+}
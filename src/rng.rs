@@ -1,4 +1,14 @@
+use std::collections::HashMap;
+
+use bevy::prelude::Resource;
 use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// 各子系统使用的随机流名称
+pub const LAYOUT: &str = "layout"; // 地图布局
+pub const CONTENT: &str = "content"; // 区域内容(怪物/物品的分布)
+pub const LOOT: &str = "loot"; // 物品的抽取与数值
+pub const GROWTH: &str = "growth"; // 升级时提供的奖励
+
 pub struct RNG(u64, u64, StdRng); // times seed stdRng
 
 impl RNG {
@@ -10,6 +20,13 @@ impl RNG {
         self.1
     }
 
+    /*
+        以当前种子和名称派生出一个独立的子随机流, 与当前随机流已消耗的次数无关
+    */
+    pub fn fork(&self, name: &str) -> RNG {
+        RNG::new(derive_seed(self.1, name))
+    }
+
     pub fn random(&mut self) -> (u64, f64) {
         self.0 += 1;
         (self.0, self.2.gen::<f64>())
@@ -57,8 +74,132 @@ impl Default for RNG {
     }
 }
 
+// FNV-1a 混合名称, 再经 splitmix64 打散, 结果不依赖平台与编译器版本
+fn derive_seed(seed: u64, name: &str) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ seed;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+// 由主种子派生出的一组命名随机流, 每个子系统只消耗自己的随机流
+pub struct RngStreams {
+    master: RNG,
+    streams: HashMap<String, RNG>,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        RngStreams {
+            master: RNG::new(seed),
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.master.seed()
+    }
+
+    pub fn stream(&mut self, name: &str) -> &mut RNG {
+        let master = &self.master;
+        self.streams
+            .entry(name.to_string())
+            .or_insert_with(|| master.fork(name))
+    }
+}
+
+impl Default for RngStreams {
+    fn default() -> Self {
+        RngStreams::new(rand::random::<u64>())
+    }
+}
+
 use std::sync::Mutex;
 
 lazy_static! {
-    pub static ref RAND: Mutex<RNG> = Mutex::new(RNG::default());
+    pub static ref RAND: Mutex<RngStreams> = Mutex::new(RngStreams::default());
+}
+
+// 本局的随机种子, 地图与内容的所有随机都由它驱动
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct RunSeed {
+    pub seed: u64,
+    pub pinned: bool, // 启动时指定的种子在重开时保持不变
+}
+
+impl RunSeed {
+    /*
+        从启动参数 `--seed <u64>` 或环境变量 `QUE_SEED` 中读取种子, 未指定则随机生成
+    */
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let from_args = args
+            .iter()
+            .position(|arg| arg == "--seed")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|seed| seed.parse::<u64>().ok());
+        let from_env = std::env::var("QUE_SEED")
+            .ok()
+            .and_then(|seed| seed.parse::<u64>().ok());
+        match from_args.or(from_env) {
+            Some(seed) => RunSeed { seed, pinned: true },
+            None => RunSeed::default(),
+        }
+    }
+
+    // 每一层地图使用的种子, 第一层直接使用本局种子
+    pub fn floor_seed(&self, floor: u32) -> u64 {
+        if floor <= 1 {
+            self.seed
+        } else {
+            derive_seed(self.seed, &format!("floor-{}", floor))
+        }
+    }
+
+    pub fn next_run(&self) -> Self {
+        if self.pinned {
+            *self
+        } else {
+            RunSeed::default()
+        }
+    }
+}
+
+impl Default for RunSeed {
+    fn default() -> Self {
+        RunSeed {
+            seed: rand::random::<u64>(),
+            pinned: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut RNG, n: usize) -> Vec<f64> {
+        (0..n).map(|_| rng.random().1).collect()
+    }
+
+    #[test]
+    fn consuming_loot_leaves_layout_unchanged() {
+        let mut untouched = RngStreams::new(42);
+        let expected = draw(untouched.stream(LAYOUT), 8);
+
+        let mut streams = RngStreams::new(42);
+        draw(streams.stream(LOOT), 100);
+        assert_eq!(draw(streams.stream(LAYOUT), 8), expected);
+    }
+
+    #[test]
+    fn streams_differ_by_name() {
+        let mut streams = RngStreams::new(42);
+        let layout = draw(streams.stream(LAYOUT), 8);
+        assert_ne!(draw(streams.stream(LOOT), 8), layout);
+    }
 }